@@ -0,0 +1,313 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::progress::{HasLength, HasProgress, Poll};
+use anyhow::{Context as _, Result, anyhow, bail};
+use std::{
+    borrow::Cow,
+    fs,
+    io::Read,
+    path::{Component, Path, PathBuf},
+    time::Instant,
+};
+use zip::{ZipArchive, read::ZipFile};
+
+/// Extract a zip archive into a directory, one entry per tick
+///
+/// Entries with an absolute path or escaping the destination directory
+/// (zip-slip) are rejected, and the unix permissions of the entries are
+/// preserved.
+pub struct Unzip {
+    archive: ZipArchive<fs::File>,
+    name: String,
+    destination: PathBuf,
+    next: usize,
+    total: u64,
+    extracted: u64,
+    started: Instant,
+}
+
+impl Unzip {
+    pub fn new(archive: impl AsRef<Path>, destination: impl Into<PathBuf>) -> Result<Self> {
+        let path = archive.as_ref();
+
+        let file = fs::File::open(path)
+            .with_context(|| anyhow!("Failed to open archive `{}'", path.display()))?;
+        let archive = ZipArchive::new(file)
+            .with_context(|| anyhow!("Invalid zip archive `{}'", path.display()))?;
+
+        let total = archive.decompressed_size().unwrap_or_default() as u64;
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let destination = destination.into();
+        tracing::debug!(
+            archive = %path.display(),
+            destination = %destination.display(),
+            entries = archive.len(),
+            "extracting archive"
+        );
+
+        Ok(Self {
+            archive,
+            name,
+            destination,
+            next: 0,
+            total,
+            extracted: 0,
+            started: Instant::now(),
+        })
+    }
+
+    /// Extract the next entry, returns `false` once every entry is extracted
+    pub fn step(&mut self) -> Result<bool> {
+        if self.next >= self.archive.len() {
+            tracing::debug!(
+                archive = %self.name,
+                bytes = self.extracted,
+                elapsed = ?self.started.elapsed(),
+                "archive extracted"
+            );
+            return Ok(false);
+        }
+
+        let index = self.next;
+        self.next += 1;
+
+        let mut entry = self
+            .archive
+            .by_index(index)
+            .with_context(|| anyhow!("Failed to read entry {index} of `{}'", self.name))?;
+        let name = entry.name().to_owned();
+
+        let extracted = extract_entry(&mut entry, &self.destination)
+            .with_context(|| anyhow!("Failed to extract `{name}' from `{}'", self.name))?;
+
+        self.extracted += extracted;
+
+        Ok(true)
+    }
+}
+
+fn extract_entry<R: Read>(entry: &mut ZipFile<'_, R>, destination: &Path) -> Result<u64> {
+    let Some(relative) = entry.enclosed_name() else {
+        bail!("Refusing to extract an entry outside of the destination directory")
+    };
+    let path = destination.join(relative);
+
+    if entry.is_dir() {
+        fs::create_dir_all(&path)
+            .with_context(|| anyhow!("Failed to create directory `{}'", path.display()))?;
+        set_permissions(&path, entry.unix_mode())?;
+        return Ok(0);
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| anyhow!("Failed to create directory `{}'", parent.display()))?;
+    }
+
+    if entry.is_symlink() {
+        let mut target = String::new();
+        entry
+            .read_to_string(&mut target)
+            .context("Failed to read the symbolic link target")?;
+
+        return create_symlink(&target, &path).map(|()| 0);
+    }
+
+    let mut file = fs::File::create(&path)
+        .with_context(|| anyhow!("Failed to create file `{}'", path.display()))?;
+    let written = std::io::copy(entry, &mut file)
+        .with_context(|| anyhow!("Failed to write file `{}'", path.display()))?;
+
+    set_permissions(&path, entry.unix_mode())?;
+
+    Ok(written)
+}
+
+#[cfg(unix)]
+fn set_permissions(path: &Path, mode: Option<u32>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let Some(mode) = mode else {
+        return Ok(());
+    };
+
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o777))
+        .with_context(|| anyhow!("Failed to set permissions of `{}'", path.display()))
+}
+
+#[cfg(not(unix))]
+fn set_permissions(_path: &Path, _mode: Option<u32>) -> Result<()> {
+    Ok(())
+}
+
+/// Create a symbolic link, only if its target stays within the directory of
+/// the link
+fn create_symlink(target: &str, path: &Path) -> Result<()> {
+    let escapes = Path::new(target).components().any(|component| {
+        matches!(
+            component,
+            Component::ParentDir | Component::RootDir | Component::Prefix(_)
+        )
+    });
+
+    if escapes {
+        bail!("Refusing to create a symbolic link to `{target}'");
+    }
+
+    tracing::trace!(link = %path.display(), target, "creating symbolic link");
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(target, path)
+            .with_context(|| anyhow!("Failed to create symbolic link `{}'", path.display()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+        bail!("Symbolic links are not supported on this platform")
+    }
+}
+
+impl HasProgress for Unzip {
+    type Output = ();
+
+    fn msg(&self) -> impl Into<Cow<'static, str>> {
+        format!("Unpacking {:?}", self.name)
+    }
+
+    async fn tick(&mut self) -> Result<Poll<Self::Output>> {
+        Ok(if self.step()? {
+            Poll::Continue
+        } else {
+            Poll::Done(())
+        })
+    }
+}
+
+impl HasLength for Unzip {
+    fn expected_length(&self) -> Option<u64> {
+        Some(self.total)
+    }
+
+    fn current_length(&self) -> u64 {
+        self.extracted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write as _;
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    fn create_archive(dir: &Path, entries: &[(&str, u32, &[u8])]) -> PathBuf {
+        let path = dir.join("artifact.zip");
+        let mut writer = ZipWriter::new(fs::File::create(&path).unwrap());
+
+        for (name, mode, content) in entries {
+            let options = SimpleFileOptions::default().unix_permissions(*mode);
+            writer.start_file(*name, options).unwrap();
+            writer.write_all(content).unwrap();
+        }
+
+        writer.finish().unwrap();
+        path
+    }
+
+    fn extract(archive: &Path, destination: &Path) -> Result<()> {
+        let mut unzip = Unzip::new(archive, destination)?;
+        while unzip.step()? {}
+        Ok(())
+    }
+
+    #[test]
+    fn extract_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = create_archive(
+            dir.path(),
+            &[
+                ("compactc", 0o755, b"#!/bin/sh\n"),
+                ("lib/std.compact", 0o644, b"module std;\n"),
+            ],
+        );
+        let destination = dir.path().join("out");
+
+        let mut unzip = Unzip::new(&archive, &destination).unwrap();
+        assert_eq!(unzip.expected_length(), Some(22));
+        while unzip.step().unwrap() {}
+        assert_eq!(unzip.current_length(), 22);
+
+        assert_eq!(
+            fs::read(destination.join("lib/std.compact")).unwrap(),
+            b"module std;\n"
+        );
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+
+            let mode = |path: &str| {
+                fs::metadata(destination.join(path))
+                    .unwrap()
+                    .permissions()
+                    .mode()
+                    & 0o777
+            };
+
+            assert_eq!(mode("compactc"), 0o755);
+            assert_eq!(mode("lib/std.compact"), 0o644);
+        }
+    }
+
+    #[test]
+    fn reject_zip_slip() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = create_archive(dir.path(), &[("../evil", 0o644, b"evil")]);
+        let destination = dir.path().join("out");
+
+        let error = extract(&archive, &destination).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Failed to extract `../evil' from `artifact.zip'"
+        );
+        assert!(!dir.path().join("evil").exists());
+    }
+
+    #[test]
+    fn reject_absolute_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let absolute = dir.path().join("evil");
+        let archive = create_archive(dir.path(), &[(absolute.to_str().unwrap(), 0o644, b"evil")]);
+
+        assert!(extract(&archive, &dir.path().join("out")).is_err());
+        assert!(!absolute.exists());
+    }
+
+    #[test]
+    fn reject_invalid_archive() {
+        let dir = tempfile::tempdir().unwrap();
+        let archive = dir.path().join("artifact.zip");
+        fs::write(&archive, b"not a zip").unwrap();
+
+        assert!(Unzip::new(&archive, dir.path()).is_err());
+    }
+}
@@ -15,33 +15,65 @@
 
 use std::{path::PathBuf, str::FromStr, sync::Arc};
 
-use anyhow::{Context as _, Result, anyhow, bail};
+use anyhow::{Context as _, Result, anyhow, bail, ensure};
 use axoupdater::AxoUpdater;
 use clap::Parser;
 use compact::{
-    COMPACT_NAME, COMPACT_VERSION, CleanCommand, Command, CommandLineArguments, CompileCommand,
-    Compiler, FixupCommand, FormatCommand, ListCommand, SSelf, UpdateCommand, VersionSpec,
+    COMPACT_NAME, COMPACT_VERSION, ChangelogCommand, CheckCommand, CleanCommand, Command,
+    CommandLineArguments, CompileCommand, Compiler, DefaultCommand, DoctorCommand, FixupCommand,
+    FormatCommand, ListCommand, SSelf, SignatureCheck, UninstallCommand, UpdateCommand,
+    Verification, VersionSpec,
+    config::ReleaseSource,
+    doctor,
     fetch::{self, MidnightArtifacts},
     file,
     fixup::{self, FixupStatus, fixup_file},
     formatter::{self, FormatStatus, format_file},
-    http, progress,
+    http, install,
+    lock::{INSTALL_LOCK, Lock},
+    output::{
+        self, AvailableVersion, ChangelogReport, CheckReport, CheckStatus, CleanReport,
+        DiagnosisStatus, DoctorReport, FileReport, FileStatus, FilesReport, InstalledReport,
+        InstalledVersion, ListReport, ProjectToolchain, ReleaseNotes, SelfReport, Toolchain,
+        UninstallReport, UpdateReport, UpdateStatus,
+    },
+    progress,
+    proxy::{self, Proxy},
+    reporter::Reporter,
+    retention::{InstalledToolchain, RetentionPolicy},
+    signature,
+    toolchain::{self, ToolchainOverride, ToolchainSource},
     utils::{self, set_current_compiler},
 };
-use indicatif::ProgressStyle;
+use indicatif::{HumanBytes, ProgressStyle};
 use tokio::task::JoinSet;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let mut args = std::env::args_os();
+
+    // invoked through one of the toolchain proxies of the `bin` directory
+    if let Some(proxy) = args.next().as_deref().and_then(Proxy::from_program) {
+        return proxy::run(proxy, args.collect()).await;
+    }
+
     let cli = CommandLineArguments::parse();
 
+    Reporter::init(&cli);
+
     match &cli.command {
-        Command::Check(_) => check(&cli)
+        Command::Check(check_command) => check(&cli, check_command)
             .await
             .context("Failed to check for new versions.")?,
         Command::Update(update_command) => update(&cli, update_command)
             .await
             .context("Failed to update")?,
+        Command::Uninstall(uninstall_command) => uninstall(&cli, uninstall_command)
+            .await
+            .context("Failed to uninstall")?,
+        Command::Default(default_command) => default(&cli, default_command)
+            .await
+            .context("Failed to set the default version")?,
         Command::Format(format_command) => format(&cli, format_command).await?,
         Command::Fixup(fixup_command) => fixup(&cli, fixup_command).await?,
         Command::SSelf(sself) => match sself {
@@ -54,6 +86,12 @@ async fn main() -> Result<()> {
         Command::Clean(clean_command) => clean(&cli, clean_command)
             .await
             .context("Failed to list available versions")?,
+        Command::Changelog(changelog_command) => changelog(&cli, changelog_command)
+            .await
+            .context("Failed to show the release notes")?,
+        Command::Doctor(doctor_command) => doctor(&cli, doctor_command)
+            .await
+            .context("Failed to diagnose the installation")?,
         Command::Compile(compile_command) => compile(&cli, compile_command)
             .await
             .context("Failed to run compactc")?,
@@ -63,6 +101,8 @@ async fn main() -> Result<()> {
 }
 
 async fn self_check(cfg: &CommandLineArguments) -> Result<()> {
+    ensure!(!cfg.offline, "Not available in offline mode");
+
     let mut updater = AxoUpdater::new_for(COMPACT_NAME);
 
     // Set GitHub token if available to avoid rate limiting
@@ -74,20 +114,26 @@ async fn self_check(cfg: &CommandLineArguments) -> Result<()> {
         .load_receipt()
         .context("Failed to load the release context")?;
 
-    if !updater.is_update_needed().await? {
-        // no update needed
-
-        println!(
-            "{label}: {target} -- {version} -- Up to date",
-            label = cfg.style.label(),
-            target = COMPACT_NAME,
-            version = cfg.style.version(COMPACT_VERSION.clone()),
-        );
-    } else {
+    let latest = if updater.is_update_needed().await? {
         let Some(latest) = updater.query_new_version().await?.cloned() else {
             bail!("Failed to query latest version")
         };
 
+        Some(latest)
+    } else {
+        None
+    };
+
+    if cfg.output.is_json() {
+        return output::print_json(&SelfReport {
+            name: COMPACT_NAME,
+            current: COMPACT_VERSION.clone(),
+            latest,
+            updated: None,
+        });
+    }
+
+    if let Some(latest) = latest {
         println!(
             "{label}: {target} -- {status} -- {version}",
             label = cfg.style.label(),
@@ -95,12 +141,23 @@ async fn self_check(cfg: &CommandLineArguments) -> Result<()> {
             status = cfg.style.warn("Update available"),
             version = cfg.style.version(latest),
         );
+    } else {
+        // no update needed
+
+        println!(
+            "{label}: {target} -- {version} -- Up to date",
+            label = cfg.style.label(),
+            target = COMPACT_NAME,
+            version = cfg.style.version(COMPACT_VERSION.clone()),
+        );
     }
 
     Ok(())
 }
 
 async fn self_update(cfg: &CommandLineArguments) -> Result<()> {
+    ensure!(!cfg.offline, "Not available in offline mode");
+
     let mut updater = AxoUpdater::new_for(COMPACT_NAME);
 
     // Set GitHub token if available to avoid rate limiting
@@ -112,7 +169,24 @@ async fn self_update(cfg: &CommandLineArguments) -> Result<()> {
         .load_receipt()
         .context("Failed to load the release context")?;
 
-    if let Some(result) = updater.run().await? {
+    let result = updater.run().await?;
+
+    if cfg.output.is_json() {
+        let latest = result.as_ref().map(|result| result.new_version.clone());
+
+        return output::print_json(&SelfReport {
+            name: COMPACT_NAME,
+            current: COMPACT_VERSION.clone(),
+            updated: Some(latest.is_some()),
+            latest,
+        });
+    }
+
+    if cfg.quiet {
+        return Ok(());
+    }
+
+    if let Some(result) = result {
         let latest = result.new_version;
 
         println!(
@@ -135,29 +209,34 @@ async fn self_update(cfg: &CommandLineArguments) -> Result<()> {
 }
 
 async fn compile(cfg: &CommandLineArguments, command: &CompileCommand) -> Result<()> {
-    let mut version: Option<semver::Version> = None;
+    let mut spec: Option<VersionSpec> = None;
     let mut args = vec![];
 
     for argument in &command.args {
         if let Some(argument) = argument.strip_prefix('+') {
-            let argument = argument.to_owned();
-            version = Some(argument.parse().context("Invalid version format")?);
+            spec = Some(argument.parse().context("Invalid version format")?);
         } else {
             args.push(argument.clone());
         }
     }
 
-    let compiler = if let Some(version) = version {
-        let target = cfg.target;
+    let target = cfg.target;
 
-        Compiler::open(cfg, version.clone(), target)
+    let compiler = match spec {
+        Some(VersionSpec::Exact(version)) => Compiler::open(cfg, version.clone(), target)
             .await
-            .with_context(|| anyhow!("Couldn't find compiler for {target} ({version})"))?
-    } else {
-        utils::get_current_compiler(cfg)
+            .with_context(|| anyhow!("Couldn't find compiler for {target} ({version})"))?,
+        Some(spec) => {
+            let version = toolchain::find_installed(cfg, &spec)
+                .await?
+                .ok_or_else(|| anyhow!("Couldn't find compiler for {target} ({spec})"))?;
+
+            Compiler::open(cfg, version, target).await?
+        }
+        None => toolchain::active_compiler(cfg)
             .await
             .context("Failed to load current compiler.")?
-            .ok_or_else(|| anyhow!("No default compiler set"))?
+            .ok_or_else(|| anyhow!("No default compiler set"))?,
     };
 
     compiler.invoke(args).await?;
@@ -168,118 +247,551 @@ async fn compile(cfg: &CommandLineArguments, command: &CompileCommand) -> Result
 async fn update(cfg: &CommandLineArguments, command: &UpdateCommand) -> Result<()> {
     utils::initialise_directories(cfg).await?;
 
+    let _lock = Lock::directory(cfg, INSTALL_LOCK).await?;
+
     // quick initial check to see if an exact version is already installed,
     // skipping network requests entirely (only works for exact versions)
-    if let Some(VersionSpec::Exact(version)) = &command.version {
-        let dir = cfg
-            .directory
-            .versions_dir()
-            .join(version.to_string())
-            .join(cfg.target.to_string());
+    if let Some(VersionSpec::Exact(version)) = &command.version
+        && install::is_installed(cfg, version, cfg.target)
+    {
+        let compiler = Compiler::open(cfg, version.clone(), cfg.target).await?;
 
-        if dir.exists() {
-            let compiler = Compiler::create(cfg, version.clone(), cfg.target).await?;
+        return finish_update(cfg, command, &compiler, UpdateStatus::AlreadyInstalled).await;
+    }
 
-            println!(
-                "{label}: {target} -- {version} -- already installed",
-                label = cfg.style.label(),
-                target = cfg.style.target(cfg.target),
-                version = cfg.style.version(version.clone()),
-            );
+    let mut artifacts = load_compilers(cfg).await?;
 
-            if !command.no_set_default {
-                set_current_compiler(cfg, &compiler).await?;
+    let (version, artifact) = match &command.version {
+        Some(spec) => resolve_version(spec, &mut artifacts, command.pre)?,
+        None if command.pre => resolve_version(&VersionSpec::Latest, &mut artifacts, true)
+            .context("No versions available")?,
+        None => resolve_version(&VersionSpec::Stable, &mut artifacts, false)
+            .context("No versions available")?,
+    };
 
-                println!(
-                    "{label}: {target} -- {version} -- {message}.",
-                    label = cfg.style.label(),
-                    target = cfg.style.target(cfg.target),
-                    version = cfg.style.version(version.clone()),
-                    message = cfg.style.success("default"),
-                );
-            }
+    let target = cfg.target;
 
-            return Ok(());
-        }
+    let installed = if install::is_installed(cfg, &version, target) {
+        false
+    } else {
+        install_compiler(cfg, command, &version, &artifact).await?;
+        true
+    };
+
+    let compiler = Compiler::open(cfg, version.clone(), target).await?;
+
+    let status = if installed {
+        UpdateStatus::Installed
+    } else {
+        UpdateStatus::AlreadyInstalled
+    };
+
+    finish_update(cfg, command, &compiler, status).await
+}
+
+/// Make the toolchain the default one unless `--no-set-default` is given,
+/// and report the update
+async fn finish_update(
+    cfg: &CommandLineArguments,
+    command: &UpdateCommand,
+    compiler: &Compiler,
+    status: UpdateStatus,
+) -> Result<()> {
+    let target = compiler.target();
+    let version = compiler.version().clone();
+
+    let previous = utils::get_current_compiler(cfg)
+        .await
+        .ok()
+        .flatten()
+        .map(|compiler| compiler.version().clone());
+
+    if !command.no_set_default {
+        set_current_compiler(cfg, compiler).await?;
     }
 
-    let mut artifacts = load_compilers().await?;
+    if cfg.output.is_json() {
+        return output::print_json(&UpdateReport {
+            version,
+            target,
+            status,
+            default: !command.no_set_default,
+        });
+    }
 
-    let (version, artifact) = match &command.version {
-        Some(spec) => resolve_version(spec, &mut artifacts)?,
+    if cfg.quiet {
+        return Ok(());
+    }
+
+    let message = match status {
+        UpdateStatus::Installed => "installed",
+        UpdateStatus::AlreadyInstalled => "already installed",
+    };
+
+    println!(
+        "{label}: {target} -- {version} -- {message}",
+        label = cfg.style.label(),
+        target = cfg.style.target(target),
+        version = cfg.style.version(version.clone()),
+    );
+
+    if !command.no_set_default {
+        println!(
+            "{label}: {target} -- {version} -- {message}.",
+            label = cfg.style.label(),
+            target = cfg.style.target(target),
+            version = cfg.style.version(version.clone()),
+            message = cfg.style.success("default"),
+        );
+    }
+
+    if let Some(previous) = previous
+        && previous < version
+    {
+        offer_whats_new(cfg, &previous, &version).await?;
+    }
+
+    Ok(())
+}
+
+/// Offer to show the release notes of the versions released after
+/// `previous`, up to `version`, when someone is at the terminal to answer
+async fn offer_whats_new(
+    cfg: &CommandLineArguments,
+    previous: &semver::Version,
+    version: &semver::Version,
+) -> Result<()> {
+    use std::io::IsTerminal as _;
+
+    if cfg.offline && ReleaseSource::load(cfg).await?.registry.is_none() {
+        return Ok(());
+    }
+
+    if !console::user_attended() || !std::io::stdin().is_terminal() {
+        return Ok(());
+    }
+
+    let show = dialoguer::Confirm::new()
+        .with_prompt(format!("Show what's new since {previous}?"))
+        .default(false)
+        .interact()
+        .unwrap_or(false);
+
+    if !show {
+        return Ok(());
+    }
+
+    let artifacts = load_compilers(cfg).await?;
+
+    let releases = artifacts
+        .compilers
+        .range((
+            std::ops::Bound::Excluded(previous),
+            std::ops::Bound::Included(version),
+        ))
+        .rev()
+        .filter(|(v, _)| v.pre.is_empty() || !version.pre.is_empty())
+        .map(|(_, compiler)| compiler);
+
+    print_release_notes(cfg, releases);
+
+    Ok(())
+}
+
+async fn changelog(cfg: &CommandLineArguments, command: &ChangelogCommand) -> Result<()> {
+    let artifacts = load_compilers(cfg).await?;
+
+    let to = match &command.to {
+        Some(to) => to.clone(),
         None => artifacts
             .compilers
-            .pop_last()
-            .ok_or_else(|| anyhow!("No versions available"))?,
+            .keys()
+            .rev()
+            .find(|version| command.pre || version.pre.is_empty())
+            .cloned()
+            .context("No version available")?,
     };
 
-    let target = cfg.target;
-
-    let compiler = Compiler::create(cfg, version.clone(), target).await?;
-    let zip_file = file::File::new(compiler.path_zip());
+    let (from, to) = if command.from <= to {
+        (command.from.clone(), to)
+    } else {
+        (to, command.from.clone())
+    };
 
-    let mut installed = false;
+    let releases: Vec<_> = artifacts
+        .compilers
+        .range(&from..=&to)
+        .rev()
+        .filter(|(version, _)| command.pre || version.pre.is_empty())
+        .map(|(_, compiler)| compiler)
+        .collect();
+
+    ensure!(!releases.is_empty(), "No release between {from} and {to}");
+
+    if cfg.output.is_json() {
+        return output::print_json(&ChangelogReport {
+            releases: releases
+                .into_iter()
+                .map(|compiler| ReleaseNotes {
+                    version: compiler.version.clone(),
+                    notes: compiler.notes.clone(),
+                })
+                .collect(),
+        });
+    }
 
-    if !compiler.path_compactc().is_file() {
-        let compiler_asset = artifact.compiler(cfg)?;
+    print_release_notes(cfg, releases);
 
-        if !zip_file.exist() {
-            let client = http::Client::new()?;
+    Ok(())
+}
 
-            let download_url = compiler_asset.download_url().clone();
-            let download_future = client.download_to_file(download_url, zip_file);
+/// Print the release notes of `releases`, in the given order
+fn print_release_notes<'a>(
+    cfg: &CommandLineArguments,
+    releases: impl IntoIterator<Item = &'a fetch::MidnightCompiler>,
+) {
+    for (index, compiler) in releases.into_iter().enumerate() {
+        if index > 0 {
+            println!();
+        }
 
-            let dl = progress::future("Downloading artifact", download_future).await?;
+        println!(
+            "{label}: {version}",
+            label = cfg.style.label(),
+            version = cfg.style.version(compiler.version.clone()),
+        );
 
-            progress::progress(dl).await?;
+        match &compiler.notes {
+            Some(notes) => println!("\n{}", notes.trim_end()),
+            None => println!("\n{}", cfg.style.warn("no release notes")),
         }
+    }
+}
 
-        let unzip_future = compiler_asset.unzip();
+async fn doctor(cfg: &CommandLineArguments, command: &DoctorCommand) -> Result<()> {
+    let report = doctor::diagnose(cfg, command.fix).await?;
 
-        progress::future("Unpacking compiler", unzip_future).await?;
+    if cfg.output.is_json() {
+        output::print_json(&report)?;
+    } else {
+        print_doctor(cfg, &report);
+    }
 
-        installed = true;
+    if report.failed() {
+        std::process::exit(1);
     }
 
-    if installed {
+    Ok(())
+}
+
+fn print_doctor(cfg: &CommandLineArguments, report: &DoctorReport) {
+    // quiet, only the problems are printed
+    let checks = report
+        .checks
+        .iter()
+        .filter(|check| !cfg.quiet || check.status != DiagnosisStatus::Pass);
+
+    for check in checks {
+        let status = match check.status {
+            DiagnosisStatus::Pass => cfg.style.success("pass"),
+            DiagnosisStatus::Warn => cfg.style.warn("warn"),
+            DiagnosisStatus::Fail => cfg.style.error("fail"),
+            DiagnosisStatus::Fixed => cfg.style.success("fixed"),
+        };
+
         println!(
-            "{label}: {target} -- {version} -- installed",
+            "{label}: {status} -- {name} -- {summary}",
             label = cfg.style.label(),
-            target = cfg.style.target(target),
-            version = cfg.style.version(version.clone()),
+            name = check.check,
+            summary = check.summary,
         );
+
+        for problem in &check.problems {
+            println!("    {problem}");
+        }
+
+        if let Some(hint) = &check.hint {
+            println!("    hint: {hint}");
+        }
+    }
+}
+
+/// Report a warning about the installation of a toolchain, on the standard
+/// error with `--output json`
+fn warn_install(
+    cfg: &CommandLineArguments,
+    target: compact::Target,
+    version: &semver::Version,
+    message: &str,
+) {
+    if cfg.output.is_json() {
+        eprintln!("Warning: {target} ({version}): {message}");
     } else {
         println!(
-            "{label}: {target} -- {version} -- already installed",
+            "{label}: {target} -- {version} -- {message}",
             label = cfg.style.label(),
             target = cfg.style.target(target),
             version = cfg.style.version(version.clone()),
+            message = cfg.style.warn(message),
         );
     }
+}
 
-    if !command.no_set_default {
-        set_current_compiler(cfg, &compiler).await?;
+/// Download, verify and unpack a toolchain in its staging directory, then
+/// move it into the versions directory
+async fn install_compiler(
+    cfg: &CommandLineArguments,
+    command: &UpdateCommand,
+    version: &semver::Version,
+    artifact: &fetch::MidnightCompiler,
+) -> Result<()> {
+    let target = cfg.target;
 
-        println!(
+    let source = ReleaseSource::load(cfg).await?;
+
+    // a local registry doesn't need the network
+    ensure!(
+        !cfg.offline || source.registry.is_some(),
+        "{target} ({version}) is not installed and can't be downloaded in offline mode"
+    );
+
+    let public_key = command
+        .public_key
+        .as_deref()
+        .or(signature::RELEASE_PUBLIC_KEY)
+        .map(signature::parse_public_key)
+        .transpose()
+        .context("Failed to load the public key verifying the artifacts")?;
+
+    let staging = install::prepare_staging(cfg, version, target).await?;
+
+    let compiler_asset = artifact.compiler(cfg, source, staging.clone())?;
+    let zip_file = file::File::new(compiler_asset.path_zip());
+
+    let client = http::Client::load(cfg).await?;
+
+    if !zip_file.exist() {
+        let download_urls = compiler_asset.download_urls();
+        let download_future = client.download_to_file_from(&download_urls, zip_file);
+
+        let dl = progress::future("Downloading artifact", download_future).await?;
+
+        progress::progress(dl).await?;
+    }
+
+    let verify_future = compiler_asset.verify(&client);
+
+    match progress::future("Verifying artifact", verify_future).await? {
+        Verification::Verified { .. } => (),
+        // older releases publish neither a checksums file nor a digest
+        Verification::NoDigest => warn_install(
+            cfg,
+            target,
+            version,
+            "no checksum published, installing unverified artifact",
+        ),
+    }
+
+    // signatures are only checked against a known key: builds without an
+    // embedded key and without `--public-key' rely on the checksum alone
+    if let Some(public_key) = &public_key {
+        let signature_future = compiler_asset.verify_signature(&client, public_key);
+
+        match progress::future("Verifying signature", signature_future).await? {
+            SignatureCheck::Verified { .. } => (),
+            SignatureCheck::Unsigned if command.allow_unsigned => warn_install(
+                cfg,
+                target,
+                version,
+                "no signature published, installing unsigned artifact",
+            ),
+            SignatureCheck::Unsigned => bail!(
+                "No signature published for {target} ({version}), use `--allow-unsigned' to install it anyway"
+            ),
+        }
+    } else {
+        tracing::debug!(%version, "no public key, signature not verified");
+    }
+
+    let unzip = compiler_asset.unzip()?;
+
+    progress::progress(unzip)
+        .await
+        .context("artifact Extraction failed")?;
+
+    install::commit(cfg, &staging, version, target)
+        .await
+        .with_context(|| anyhow!("Failed to install {target} ({version})"))
+}
+
+async fn default(cfg: &CommandLineArguments, command: &DefaultCommand) -> Result<()> {
+    let Some(spec) = &command.version else {
+        let current = utils::get_current_compiler(cfg)
+            .await
+            .context("Failed to get the current compiler")?;
+
+        return print_default(cfg, current.as_ref());
+    };
+
+    utils::initialise_directories(cfg).await?;
+
+    let _lock = Lock::directory(cfg, INSTALL_LOCK).await?;
+
+    let Some(version) = toolchain::find_installed(cfg, spec).await? else {
+        let installed = utils::installed_versions(cfg).await?;
+
+        if installed.is_empty() {
+            bail!("No version is installed, try `compact update {spec}'");
+        }
+
+        let installed = installed
+            .iter()
+            .rev()
+            .map(|version| version.to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        bail!(
+            "No installed version matches {spec}, try `compact update {spec}' (installed: {installed})"
+        );
+    };
+
+    let compiler = Compiler::open(cfg, version, cfg.target).await?;
+    let compiler = set_current_compiler(cfg, &compiler).await?;
+
+    if cfg.quiet && !cfg.output.is_json() {
+        return Ok(());
+    }
+
+    print_default(cfg, Some(&compiler))
+}
+
+/// Report the default toolchain
+fn print_default(cfg: &CommandLineArguments, compiler: Option<&Compiler>) -> Result<()> {
+    let toolchain = compiler.map(|compiler| Toolchain {
+        version: compiler.version().clone(),
+        target: compiler.target(),
+    });
+
+    if cfg.output.is_json() {
+        return output::print_json(&toolchain);
+    }
+
+    match toolchain {
+        Some(Toolchain { version, target }) => println!(
             "{label}: {target} -- {version} -- {message}.",
             label = cfg.style.label(),
             target = cfg.style.target(target),
             version = cfg.style.version(version),
             message = cfg.style.success("default"),
-        );
+        ),
+        None => println!(
+            "{label}: {message}.",
+            label = cfg.style.label(),
+            message = cfg.style.warn("no default version"),
+        ),
+    }
+
+    Ok(())
+}
+
+async fn uninstall(cfg: &CommandLineArguments, command: &UninstallCommand) -> Result<()> {
+    utils::initialise_directories(cfg).await?;
+
+    let _lock = Lock::directory(cfg, INSTALL_LOCK).await?;
+
+    let target = command.target.unwrap_or(cfg.target);
+    let spec = &command.version;
+
+    let installed = utils::installed_versions_for(cfg, target).await?;
+    let version = spec
+        .select(installed.iter(), true)
+        .cloned()
+        .ok_or_else(|| anyhow!("No version matching {spec} is installed for {target}"))?;
+
+    // compare with the recorded default: it may be broken and fail to open
+    let was_default = utils::get_default_toolchain(cfg)
+        .await
+        .context("Failed to read the default toolchain")?
+        .is_some_and(|default| default == (version.clone(), target));
+
+    install::uninstall(cfg, &version, target).await?;
+
+    let default = if was_default {
+        let remaining = utils::installed_versions_for(cfg, target).await?;
+
+        match remaining.last() {
+            Some(next) => {
+                let compiler = Compiler::open(cfg, next.clone(), target).await?;
+                Some(set_current_compiler(cfg, &compiler).await?)
+            }
+            None => {
+                utils::unset_current_compiler(cfg).await?;
+                None
+            }
+        }
+    } else {
+        // another default is left as it is, even when it can't be opened
+        utils::get_current_compiler(cfg).await.ok().flatten()
+    };
+
+    if cfg.output.is_json() {
+        return output::print_json(&UninstallReport {
+            version,
+            target,
+            default: default.map(|compiler| Toolchain {
+                version: compiler.version().clone(),
+                target: compiler.target(),
+            }),
+            default_changed: was_default,
+        });
+    }
+
+    if cfg.quiet {
+        return Ok(());
+    }
+
+    println!(
+        "{label}: {target} -- {version} -- {message}",
+        label = cfg.style.label(),
+        target = cfg.style.target(target),
+        version = cfg.style.version(version.clone()),
+        message = cfg.style.error("uninstalled"),
+    );
+
+    if was_default {
+        match default {
+            Some(compiler) => println!(
+                "{label}: {target} -- {version} -- {message}.",
+                label = cfg.style.label(),
+                target = cfg.style.target(compiler.target()),
+                version = cfg.style.version(compiler.version().clone()),
+                message = cfg.style.success("default"),
+            ),
+            None => println!(
+                "{label}: {message}.",
+                label = cfg.style.label(),
+                message = cfg.style.warn("no default version"),
+            ),
+        }
     }
 
     Ok(())
 }
 
 async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<()> {
-    let bin = cfg.directory.bin_dir().join("format-compact");
+    let bin = toolchain::active_compiler(cfg)
+        .await
+        .context("Failed to load current compiler.")?
+        .inspect(Compiler::mark_used)
+        .map(|compiler| compiler.path_format_compact())
+        .filter(|bin| bin.exists());
 
-    if !bin.exists() {
+    let Some(bin) = bin else {
         bail!(
             "formatter not available - please install a compiler version that includes format-compact"
         )
-    }
+    };
 
     if command.version || command.language_version {
         let flag = if command.version {
@@ -288,9 +800,7 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
             "--language-version"
         };
 
-        let output = tokio::process::Command::new(&bin)
-            .arg(flag)
-            .output()
+        let output = utils::command_output(tokio::process::Command::new(&bin).arg(flag))
             .await
             .context("Failed to invoke format-compact")?;
 
@@ -324,6 +834,8 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
         }
     }
 
+    let json = cfg.output.is_json();
+    let mut report = FilesReport::default();
     let mut something_failed = false;
 
     while let Some(result) = join_set.join_next().await {
@@ -339,6 +851,24 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
             continue;
         };
 
+        if json {
+            let (status, diff) = match style {
+                FormatStatus::Error => (FileStatus::Failed, None),
+                FormatStatus::Success => (FileStatus::Changed, None),
+                FormatStatus::Warn => (FileStatus::Unchanged, None),
+                // the diff is styled for the terminal, not for the report
+                FormatStatus::Diff(diff) => (
+                    FileStatus::NeedsChanges,
+                    Some(console::strip_ansi_codes(&diff).into_owned()),
+                ),
+            };
+
+            something_failed |= matches!(status, FileStatus::Failed | FileStatus::NeedsChanges);
+            report.files.push(FileReport { path, status, diff });
+
+            continue;
+        }
+
         match style {
             FormatStatus::Error => {
                 eprintln!(
@@ -349,14 +879,14 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
 
                 something_failed = true;
             }
-            FormatStatus::Success if command.verbose => {
+            FormatStatus::Success if cfg.verbose > 0 => {
                 println!(
                     "{}: {}",
                     cfg.style.version_raw(path.display()),
                     cfg.style.success(message)
                 );
             }
-            FormatStatus::Warn if command.verbose => {
+            FormatStatus::Warn if cfg.verbose > 0 => {
                 println!(
                     "{}: {}",
                     cfg.style.version_raw(path.display()),
@@ -372,6 +902,12 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
         }
     }
 
+    if json {
+        report.files.sort_by(|a, b| a.path.cmp(&b.path));
+        report.success = !something_failed;
+        output::print_json(&report)?;
+    }
+
     if something_failed {
         bail!("formatting failed")
     } else {
@@ -380,13 +916,18 @@ async fn format(cfg: &CommandLineArguments, command: &FormatCommand) -> Result<(
 }
 
 async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()> {
-    let bin = cfg.directory.bin_dir().join("fixup-compact");
+    let bin = toolchain::active_compiler(cfg)
+        .await
+        .context("Failed to load current compiler.")?
+        .inspect(Compiler::mark_used)
+        .map(|compiler| compiler.path_fixup_compact())
+        .filter(|bin| bin.exists());
 
-    if !bin.exists() {
+    let Some(bin) = bin else {
         bail!(
             "fixup tool not available - please install a compiler version that includes fixup-compact"
         )
-    }
+    };
 
     if command.version || command.language_version {
         let flag = if command.version {
@@ -395,9 +936,7 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
             "--language-version"
         };
 
-        let output = tokio::process::Command::new(&bin)
-            .arg(flag)
-            .output()
+        let output = utils::command_output(tokio::process::Command::new(&bin).arg(flag))
             .await
             .context("Failed to invoke fixup-compact")?;
 
@@ -435,6 +974,8 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
         }
     }
 
+    let json = cfg.output.is_json();
+    let mut report = FilesReport::default();
     let mut something_failed = false;
 
     while let Some(result) = join_set.join_next().await {
@@ -448,6 +989,22 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
             continue;
         };
 
+        if json {
+            let (status, diff) = match status {
+                FixupStatus::Error => (FileStatus::Failed, None),
+                FixupStatus::Success => (FileStatus::Changed, None),
+                FixupStatus::Unchanged => (FileStatus::Unchanged, None),
+                FixupStatus::Diff(diff) => (
+                    FileStatus::NeedsChanges,
+                    Some(console::strip_ansi_codes(&diff).into_owned()),
+                ),
+            };
+
+            something_failed |= matches!(status, FileStatus::Failed | FileStatus::NeedsChanges);
+            report.files.push(FileReport { path, status, diff });
+            continue;
+        }
+
         match status {
             FixupStatus::Error => {
                 eprintln!(
@@ -457,14 +1014,14 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
                 );
                 something_failed = true;
             }
-            FixupStatus::Success if command.verbose => {
+            FixupStatus::Success if cfg.verbose > 0 => {
                 println!(
                     "{}: {}",
                     cfg.style.version_raw(path.display()),
                     cfg.style.success(message)
                 );
             }
-            FixupStatus::Unchanged if command.verbose => {
+            FixupStatus::Unchanged if cfg.verbose > 0 => {
                 println!(
                     "{}: {}",
                     cfg.style.version_raw(path.display()),
@@ -480,6 +1037,12 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
         }
     }
 
+    if json {
+        report.files.sort_by(|a, b| a.path.cmp(&b.path));
+        report.success = !something_failed;
+        output::print_json(&report)?;
+    }
+
     if something_failed {
         bail!("fixup failed")
     } else {
@@ -490,6 +1053,7 @@ async fn fixup(cfg: &CommandLineArguments, command: &FixupCommand) -> Result<()>
 fn resolve_version(
     spec: &VersionSpec,
     artifacts: &mut MidnightArtifacts,
+    allow_pre: bool,
 ) -> Result<(semver::Version, fetch::MidnightCompiler)> {
     match spec {
         VersionSpec::Exact(version) => artifacts
@@ -497,12 +1061,7 @@ fn resolve_version(
             .remove_entry(version)
             .ok_or_else(|| anyhow!("Couldn't find version {version}")),
         _ => {
-            let matched = artifacts
-                .compilers
-                .keys()
-                .rev()
-                .find(|v| spec.matches(v))
-                .cloned();
+            let matched = spec.select(artifacts.compilers.keys(), allow_pre).cloned();
 
             match matched {
                 Some(version) => artifacts
@@ -515,8 +1074,8 @@ fn resolve_version(
     }
 }
 
-async fn load_compilers() -> Result<MidnightArtifacts> {
-    let pb = indicatif::ProgressBar::new_spinner();
+async fn load_compilers(cfg: &CommandLineArguments) -> Result<MidnightArtifacts> {
+    let pb = progress::spinner();
 
     pb.set_style(ProgressStyle::default_spinner().tick_chars(" ▏▎▍▌▋▊▉█"));
 
@@ -524,7 +1083,7 @@ async fn load_compilers() -> Result<MidnightArtifacts> {
 
     pb.set_message("Fetching information from server");
 
-    let artifacts = fetch::MidnightArtifacts::load().await.with_context(|| {
+    let artifacts = fetch::MidnightArtifacts::load(cfg).await.with_context(|| {
         anyhow!("Failed to query backend services to collect the latest artifacts")
     })?;
 
@@ -533,46 +1092,155 @@ async fn load_compilers() -> Result<MidnightArtifacts> {
     Ok(artifacts)
 }
 
-async fn check(cfg: &CommandLineArguments) -> Result<()> {
+async fn check(cfg: &CommandLineArguments, command: &CheckCommand) -> Result<()> {
     utils::initialise_directories(cfg).await?;
 
     let current_compiler = utils::get_current_compiler(cfg)
         .await
         .context("Failed to get the current compiler")?;
 
-    let mut artifacts = load_compilers().await?;
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+    let pin = ToolchainOverride::find(&cwd)
+        .await
+        .context("Failed to read the project toolchain")?;
+    let pinned_version = match &pin {
+        Some(pin) => toolchain::find_installed(cfg, &pin.spec).await?,
+        None => None,
+    };
 
-    let Some((latest_version, _)) = artifacts.compilers.pop_last() else {
+    let artifacts = load_compilers(cfg).await?;
+
+    // the version `compact update' installs by default
+    let Some(latest_version) = VersionSpec::Stable.select(artifacts.compilers.keys(), false) else {
         bail!("No version available")
     };
 
-    let show_latest: bool;
+    // with a project pin, only the versions it accepts are relevant
+    let (version, latest_version) = match &pin {
+        Some(pin) => (
+            pinned_version.clone(),
+            pin.spec
+                .select(artifacts.compilers.keys(), false)
+                .or(pinned_version.as_ref())
+                .unwrap_or(latest_version)
+                .clone(),
+        ),
+        None => (
+            current_compiler.as_ref().map(|c| c.version().clone()),
+            latest_version.clone(),
+        ),
+    };
 
-    if let Some(compiler) = current_compiler {
-        let target = compiler.target();
-        let version = compiler.version().clone();
-        let status = if version >= latest_version {
-            show_latest = false;
-            cfg.style.success("Up to date")
-        } else {
-            show_latest = true;
-            cfg.style.warn("Update Available")
-        };
+    let update_available = version
+        .as_ref()
+        .is_none_or(|version| version < &latest_version);
 
-        println!(
-            "{label}: {target} -- {status} -- {version}",
-            label = cfg.style.label(),
-            target = cfg.style.target(target),
-            version = cfg.style.version(version),
-        );
+    let pin_mismatch = pin.as_ref().is_some_and(|pin| {
+        current_compiler
+            .as_ref()
+            .is_some_and(|compiler| !pin.spec.matches(compiler.version()))
+    });
+
+    let status = if version.is_none() {
+        CheckStatus::NoToolchain
+    } else if pin_mismatch {
+        CheckStatus::PinMismatch
+    } else if update_available {
+        CheckStatus::UpdateAvailable
     } else {
-        show_latest = true;
+        CheckStatus::UpToDate
+    };
 
-        println!(
-            "{label}: {message}.",
-            label = cfg.style.label(),
-            message = cfg.style.warn("no version installed"),
-        );
+    if cfg.output.is_json() {
+        output::print_json(&CheckReport {
+            current: current_compiler.map(|compiler| Toolchain {
+                version: compiler.version().clone(),
+                target: compiler.target(),
+            }),
+            project: pin.map(|pin| ProjectToolchain {
+                spec: pin.spec.to_string(),
+                source: match pin.source {
+                    ToolchainSource::Environment => toolchain::TOOLCHAIN_ENV.to_owned(),
+                    ToolchainSource::File(path) => path.display().to_string(),
+                },
+                version: pinned_version,
+            }),
+            latest: latest_version,
+            update_available,
+            status,
+        })?;
+    } else if !cfg.quiet {
+        print_check(cfg, current_compiler, pin, version, latest_version);
+    }
+
+    match status.exit_code(command.fail_on_outdated) {
+        0 => Ok(()),
+        code => std::process::exit(code),
+    }
+}
+
+fn print_check(
+    cfg: &CommandLineArguments,
+    current_compiler: Option<Compiler>,
+    pin: Option<ToolchainOverride>,
+    version: Option<semver::Version>,
+    latest_version: semver::Version,
+) {
+    let show_latest = version
+        .as_ref()
+        .is_none_or(|version| version < &latest_version);
+    let status = if show_latest {
+        cfg.style.warn("Update Available")
+    } else {
+        cfg.style.success("Up to date")
+    };
+
+    match (&pin, &current_compiler) {
+        (None, Some(compiler)) => {
+            println!(
+                "{label}: {target} -- {status} -- {version}",
+                label = cfg.style.label(),
+                target = cfg.style.target(compiler.target()),
+                version = cfg.style.version(compiler.version().clone()),
+            );
+        }
+        (None, None) => {
+            println!(
+                "{label}: {message}.",
+                label = cfg.style.label(),
+                message = cfg.style.warn("no version installed"),
+            );
+        }
+        (Some(pin), _) => {
+            match version {
+                Some(version) => println!(
+                    "{label}: {target} -- {status} -- {version} (required by {source})",
+                    label = cfg.style.label(),
+                    target = cfg.style.target(cfg.target),
+                    version = cfg.style.version(version),
+                    source = pin.source,
+                ),
+                None => println!(
+                    "{label}: {message} {spec} required by {source}.",
+                    label = cfg.style.label(),
+                    message = cfg.style.warn("no version installed matching"),
+                    spec = pin.spec,
+                    source = pin.source,
+                ),
+            }
+
+            if let Some(compiler) = current_compiler
+                && !pin.spec.matches(compiler.version())
+            {
+                println!(
+                    "{label}: {target} -- {message} -- {version}",
+                    label = cfg.style.label(),
+                    target = cfg.style.target(compiler.target()),
+                    message = cfg.style.warn("Default doesn't match the project"),
+                    version = cfg.style.version(compiler.version().clone()),
+                );
+            }
+        }
     }
 
     if show_latest {
@@ -582,8 +1250,6 @@ async fn check(cfg: &CommandLineArguments) -> Result<()> {
             version = cfg.style.version(latest_version),
         );
     }
-
-    Ok(())
 }
 
 async fn list(cfg: &CommandLineArguments, command: &ListCommand) -> Result<()> {
@@ -596,11 +1262,13 @@ async fn list(cfg: &CommandLineArguments, command: &ListCommand) -> Result<()> {
     if command.installed {
         utils::initialise_directories(cfg).await?;
 
-        println!(
-            "{label}: {message}\n",
-            label = cfg.style.label(),
-            message = cfg.style.artifact("installed versions")
-        );
+        if !cfg.output.is_json() {
+            println!(
+                "{label}: {message}\n",
+                label = cfg.style.label(),
+                message = cfg.style.artifact("installed versions")
+            );
+        }
 
         let dir = cfg.directory.versions_dir();
 
@@ -642,6 +1310,24 @@ async fn list(cfg: &CommandLineArguments, command: &ListCommand) -> Result<()> {
             }
         });
 
+        if cfg.output.is_json() {
+            let versions = all_entries
+                .iter()
+                .map(|entry| entry.path())
+                .filter(|path| path.is_dir())
+                .map(|path| {
+                    let version = path.file_name().unwrap().to_string_lossy().into_owned();
+                    let default = current_compiler
+                        .as_ref()
+                        .is_some_and(|c| c.version().to_string() == version);
+
+                    InstalledVersion { version, default }
+                })
+                .collect();
+
+            return output::print_json(&InstalledReport { versions });
+        }
+
         let mut total = 0;
 
         for entry in all_entries {
@@ -691,7 +1377,32 @@ async fn list(cfg: &CommandLineArguments, command: &ListCommand) -> Result<()> {
             );
         }
     } else {
-        let artifacts = load_compilers().await?;
+        let artifacts = load_compilers(cfg).await?;
+
+        if cfg.output.is_json() {
+            let versions = artifacts
+                .compilers
+                .into_iter()
+                .rev()
+                .map(|(version, compiler)| AvailableVersion {
+                    platforms: [
+                        compiler.x86_macos.as_ref().map(|_| "x86_macos"),
+                        compiler.aarch64_macos.as_ref().map(|_| "aarch64_macos"),
+                        compiler.x86_linux.as_ref().map(|_| "x86_linux"),
+                        compiler.aarch64_linux.as_ref().map(|_| "aarch64_linux"),
+                    ]
+                    .into_iter()
+                    .flatten()
+                    .collect(),
+                    default: current_compiler
+                        .as_ref()
+                        .is_some_and(|c| c.version() == &version),
+                    version,
+                })
+                .collect();
+
+            return output::print_json(&ListReport { versions });
+        }
 
         println!(
             "{label}: {message}\n",
@@ -755,45 +1466,80 @@ async fn list(cfg: &CommandLineArguments, command: &ListCommand) -> Result<()> {
 async fn clean(cfg: &CommandLineArguments, command: &CleanCommand) -> Result<()> {
     utils::initialise_directories(cfg).await?;
 
-    if command.cache {
-        let cache_path = fetch::get_cache_path()?;
+    let _lock = Lock::directory(cfg, INSTALL_LOCK).await?;
 
-        utils::remove_file_if_exists(&cache_path).await?;
+    let dry_run = command.dry_run;
 
-        println!(
-            "{label}: {message} {version}",
-            label = cfg.style.label(),
-            message = cfg.style.error("removed"),
-            version = cfg.style.version_raw(cache_path.display()).italic().dim()
-        );
+    // leftovers of interrupted installations
+    if !dry_run {
+        install::clean_staging(cfg, None).await?;
+    }
+
+    let json = cfg.output.is_json();
+    // what a dry run would remove is the requested data
+    let silent = json || (cfg.quiet && !dry_run);
+    let mut report = CleanReport {
+        dry_run,
+        ..Default::default()
+    };
+
+    let removed_message = if dry_run {
+        cfg.style.warn("would remove")
+    } else {
+        cfg.style.error("removed")
+    };
+
+    if command.cache {
+        report.cache = if dry_run {
+            fetch::removable_cache_files(cfg).await?
+        } else {
+            fetch::clear_cache(cfg).await?
+        };
+
+        for cache_path in report.cache.iter().filter(|_| !silent) {
+            println!(
+                "{label}: {message} {version}",
+                label = cfg.style.label(),
+                message = removed_message,
+                version = cfg.style.version_raw(cache_path.display()).italic().dim()
+            );
+        }
     }
 
     let versions_dir = cfg.directory.versions_dir();
-    let sym_bin = cfg.directory.bin_dir().join("compactc");
 
     let mut entries = tokio::fs::read_dir(&versions_dir)
         .await
         .context("Failed to load installed versions")?;
 
-    println!(
-        "{label}: {message}",
-        label = cfg.style.label(),
-        message = cfg.style.artifact("removing versions")
-    );
+    if !silent {
+        println!(
+            "{label}: {message}",
+            label = cfg.style.label(),
+            message = cfg.style.artifact("removing versions")
+        );
+    }
 
-    let (_, current_version) = utils::read_parent_name_from_link(&sym_bin)
+    let current_version = utils::get_current_compiler(cfg)
         .await
-        .unwrap_or_default();
-
-    if !command.keep_current {
-        utils::remove_file_if_exists(&sym_bin).await?;
+        .ok()
+        .flatten()
+        .map(|compiler| compiler.version().clone());
 
-        let format_bin = cfg.directory.bin_dir().join("format-compact");
-        let fixup_bin = cfg.directory.bin_dir().join("fixup-compact");
+    // the toolchain of the current project is never removed
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+    let pinned_version = match ToolchainOverride::find(&cwd)
+        .await
+        .context("Failed to read the project toolchain")?
+    {
+        Some(pin) => toolchain::find_installed(cfg, &pin.spec).await?,
+        None => None,
+    };
 
-        utils::remove_file_if_exists(&format_bin).await?;
-        utils::remove_file_if_exists(&fixup_bin).await?;
-    }
+    let protected = pinned_version
+        .into_iter()
+        .chain(current_version.clone().filter(|_| command.keep_current))
+        .collect();
 
     let mut all_entries = Vec::new();
     while let Some(entry) = entries
@@ -829,40 +1575,80 @@ async fn clean(cfg: &CommandLineArguments, command: &CleanCommand) -> Result<()>
         }
     });
 
+    let mut toolchains = Vec::new();
+
     for entry in all_entries {
         let path = entry.path();
 
-        let display_name = path
-            .file_name()
-            .and_then(|l| l.to_str())
-            .map(|l| l.to_string())
-            .unwrap_or_default();
+        if path.is_dir() {
+            toolchains.push(InstalledToolchain::load(path).await?);
+        } else if path.is_file() && !dry_run {
+            tokio::fs::remove_file(path)
+                .await
+                .context("Failed to remove unknown file")?;
+        }
+    }
+
+    let policy = RetentionPolicy::new(command, protected);
+    let retained = policy.retain(&toolchains, std::time::SystemTime::now());
+
+    for (toolchain, keep) in toolchains.into_iter().zip(retained) {
+        if keep {
+            if !silent {
+                println!(
+                    "{label}: {message} {version}",
+                    label = cfg.style.label(),
+                    message = cfg.style.success("kept"),
+                    version = cfg.style.version_raw(&toolchain.name).italic().dim()
+                );
+            }
+
+            report.kept.push(toolchain.name);
+            continue;
+        }
 
-        let keep_entry = command.keep_current && display_name.contains(&current_version);
+        let size = utils::disk_usage(&toolchain.path).await?;
+        report.reclaimed += size;
 
-        if !keep_entry && path.is_dir() {
-            tokio::fs::remove_dir_all(&path)
+        if !dry_run {
+            tokio::fs::remove_dir_all(&toolchain.path)
                 .await
                 .context("Failed to remove version")?;
+        }
 
+        if !silent {
             println!(
-                "{label}: {message} {version}",
-                label = cfg.style.label(),
-                message = cfg.style.error("removed"),
-                version = cfg.style.version_raw(display_name).italic().dim()
-            );
-        } else if path.is_file() {
-            tokio::fs::remove_file(path)
-                .await
-                .context("Failed to remove unknown file")?;
-        } else {
-            println!(
-                "{label}: {message} {version}",
+                "{label}: {message} {version}{size}",
                 label = cfg.style.label(),
-                message = cfg.style.success("kept"),
-                version = cfg.style.version_raw(display_name).italic().dim()
+                message = removed_message,
+                version = cfg.style.version_raw(&toolchain.name).italic().dim(),
+                size = if dry_run {
+                    format!(" ({})", HumanBytes(size))
+                } else {
+                    String::new()
+                },
             );
         }
+
+        report.removed.push(toolchain.name);
+    }
+
+    let current_kept = current_version
+        .is_some_and(|version| report.kept.iter().any(|name| name == &version.to_string()));
+
+    if !dry_run && !current_kept {
+        utils::unset_current_compiler(cfg).await?;
+    }
+
+    if json {
+        output::print_json(&report)?;
+    } else if dry_run {
+        println!(
+            "{label}: {message} {size}",
+            label = cfg.style.label(),
+            message = cfg.style.warn("would reclaim"),
+            size = HumanBytes(report.reclaimed),
+        );
     }
 
     Ok(())
@@ -880,6 +1666,9 @@ mod tests {
             aarch64_macos: None,
             x86_linux: None,
             aarch64_linux: None,
+            checksums: Vec::new(),
+            signatures: Vec::new(),
+            notes: None,
         }
     }
 
@@ -896,7 +1685,7 @@ mod tests {
     fn resolve_exact_version_found() {
         let mut artifacts = make_artifacts(&["0.28.0", "0.29.0", "0.29.1"]);
         let spec = VersionSpec::Exact(Version::new(0, 29, 0));
-        let (v, _) = resolve_version(&spec, &mut artifacts).unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
         assert_eq!(v, Version::new(0, 29, 0));
     }
 
@@ -904,7 +1693,7 @@ mod tests {
     fn resolve_exact_version_not_found() {
         let mut artifacts = make_artifacts(&["0.28.0", "0.29.0"]);
         let spec = VersionSpec::Exact(Version::new(0, 30, 0));
-        let err = resolve_version(&spec, &mut artifacts).unwrap_err();
+        let err = resolve_version(&spec, &mut artifacts, false).unwrap_err();
         assert!(err.to_string().contains("0.30.0"));
     }
 
@@ -915,7 +1704,7 @@ mod tests {
             major: 0,
             minor: 29,
         };
-        let (v, _) = resolve_version(&spec, &mut artifacts).unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
         assert_eq!(v, Version::new(0, 29, 2));
     }
 
@@ -926,7 +1715,7 @@ mod tests {
             major: 0,
             minor: 29,
         };
-        let (v, _) = resolve_version(&spec, &mut artifacts).unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
         assert_eq!(v, Version::new(0, 29, 0));
     }
 
@@ -937,7 +1726,7 @@ mod tests {
             major: 0,
             minor: 30,
         };
-        let err = resolve_version(&spec, &mut artifacts).unwrap_err();
+        let err = resolve_version(&spec, &mut artifacts, false).unwrap_err();
         assert!(err.to_string().contains("0.30"));
     }
 
@@ -946,7 +1735,7 @@ mod tests {
         let mut artifacts =
             make_artifacts(&["0.28.0", "0.29.0", "0.29.1", "1.0.0", "1.1.0", "1.1.1"]);
         let spec = VersionSpec::Major { major: 1 };
-        let (v, _) = resolve_version(&spec, &mut artifacts).unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
         assert_eq!(v, Version::new(1, 1, 1));
     }
 
@@ -954,7 +1743,7 @@ mod tests {
     fn resolve_major_single_version() {
         let mut artifacts = make_artifacts(&["0.28.0", "1.0.0"]);
         let spec = VersionSpec::Major { major: 1 };
-        let (v, _) = resolve_version(&spec, &mut artifacts).unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
         assert_eq!(v, Version::new(1, 0, 0));
     }
 
@@ -962,7 +1751,40 @@ mod tests {
     fn resolve_major_not_found() {
         let mut artifacts = make_artifacts(&["0.28.0", "0.29.0"]);
         let spec = VersionSpec::Major { major: 1 };
-        let err = resolve_version(&spec, &mut artifacts).unwrap_err();
+        let err = resolve_version(&spec, &mut artifacts, false).unwrap_err();
         assert!(err.to_string().contains("No version matching 1 found"));
     }
+
+    #[test]
+    fn resolve_requirement_picks_highest_match() {
+        let mut artifacts = make_artifacts(&["0.28.0", "0.29.0", "0.30.1", "0.31.0"]);
+        let spec: VersionSpec = ">=0.28, <0.31".parse().unwrap();
+        let (v, _) = resolve_version(&spec, &mut artifacts, false).unwrap();
+        assert_eq!(v, Version::new(0, 30, 1));
+    }
+
+    #[test]
+    fn resolve_channels() {
+        let mut artifacts = make_artifacts(&["0.29.0", "0.30.0-rc.1"]);
+        let (v, _) = resolve_version(&VersionSpec::Stable, &mut artifacts, false).unwrap();
+        assert_eq!(v, Version::new(0, 29, 0));
+
+        let mut artifacts = make_artifacts(&["0.29.0", "0.30.0-rc.1"]);
+        let (v, _) = resolve_version(&VersionSpec::Latest, &mut artifacts, false).unwrap();
+        assert_eq!(v, Version::parse("0.30.0-rc.1").unwrap());
+    }
+
+    #[test]
+    fn resolve_partial_with_pre_releases() {
+        let spec = VersionSpec::Partial {
+            major: 0,
+            minor: 30,
+        };
+
+        let mut artifacts = make_artifacts(&["0.29.0", "0.30.0-rc.1"]);
+        assert!(resolve_version(&spec, &mut artifacts, false).is_err());
+
+        let (v, _) = resolve_version(&spec, &mut artifacts, true).unwrap();
+        assert_eq!(v, Version::parse("0.30.0-rc.1").unwrap());
+    }
 }
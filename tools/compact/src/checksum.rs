@@ -0,0 +1,172 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! SHA-256 checksums of release assets
+//!
+//! Releases may publish the expected digests of their assets in a checksums
+//! asset, either one file per asset (`<asset>.sha256`) or a single file
+//! listing every asset (`SHA256SUMS`, `checksums.txt`), using the format of
+//! `sha256sum`:
+//!
+//! ```text
+//! 3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b  compactc_v0.29.0_x86_64-unknown-linux-musl.zip
+//! ```
+//!
+//! GitHub also reports the digest of every asset in the release metadata.
+
+use anyhow::{Context, Result, anyhow, ensure};
+use sha2::{Digest as _, Sha256};
+use std::{fmt, path::Path, str::FromStr};
+
+/// A SHA-256 digest
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sha256Digest([u8; 32]);
+
+impl Sha256Digest {
+    /// Compute the digest of the content of the file at `path`
+    pub async fn of_file(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+
+        tokio::task::spawn_blocking(move || {
+            let mut file = std::fs::File::open(&path)
+                .with_context(|| anyhow!("Failed to open file `{}'", path.display()))?;
+            let mut hasher = Sha256::new();
+
+            std::io::copy(&mut file, &mut hasher)
+                .with_context(|| anyhow!("Failed to read file `{}'", path.display()))?;
+
+            Ok(Self(hasher.finalize().into()))
+        })
+        .await
+        .context("Failed to compute the checksum")?
+    }
+}
+
+impl FromStr for Sha256Digest {
+    type Err = anyhow::Error;
+
+    /// Parse an hexadecimal digest, optionally prefixed with `sha256:` as in
+    /// the GitHub release metadata
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hex = s.strip_prefix("sha256:").unwrap_or(s);
+
+        ensure!(hex.len() == 64, "Invalid SHA-256 digest `{s}'");
+
+        let mut digest = [0; 32];
+        hex::decode_to_slice(hex, &mut digest)
+            .with_context(|| anyhow!("Invalid SHA-256 digest `{s}'"))?;
+
+        Ok(Self(digest))
+    }
+}
+
+impl fmt::Display for Sha256Digest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        hex::encode(self.0).fmt(f)
+    }
+}
+
+/// Returns true if the asset `name` is a checksums file
+pub fn is_checksums_file(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+
+    name.ends_with(".sha256")
+        || name.ends_with(".sha256sum")
+        || name.contains("sha256sums")
+        || name.ends_with("checksums.txt")
+}
+
+/// Find the digest of `file_name` in the content of a checksums file
+///
+/// A line with a digest alone is accepted too, as published in files holding
+/// the checksum of a single asset.
+pub fn find_checksum(content: &str, file_name: &str) -> Option<Sha256Digest> {
+    content.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        let digest = fields.next()?;
+
+        match fields.next() {
+            // `sha256sum` marks files read in binary mode with a `*`
+            Some(name) if name.trim_start_matches('*') != file_name => None,
+            _ => digest.parse().ok(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIGEST: &str = "3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b";
+    const OTHER: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn parse_digest() {
+        let digest: Sha256Digest = DIGEST.parse().unwrap();
+        assert_eq!(digest.to_string(), DIGEST);
+
+        let prefixed: Sha256Digest = format!("sha256:{DIGEST}").parse().unwrap();
+        assert_eq!(prefixed, digest);
+
+        assert!("sha256:abc".parse::<Sha256Digest>().is_err());
+        assert!(DIGEST.replace('a', "z").parse::<Sha256Digest>().is_err());
+    }
+
+    #[test]
+    fn checksums_files() {
+        assert!(is_checksums_file("SHA256SUMS"));
+        assert!(is_checksums_file("checksums.txt"));
+        assert!(is_checksums_file(
+            "compactc_v0.29.0_x86_64-unknown-linux-musl.zip.sha256"
+        ));
+        assert!(!is_checksums_file(
+            "compactc_v0.29.0_x86_64-unknown-linux-musl.zip"
+        ));
+    }
+
+    #[test]
+    fn find_in_checksums_file() {
+        let content = format!("{OTHER}  compactc-aarch64.zip\n{DIGEST} *compactc-x86_64.zip\n");
+
+        assert_eq!(
+            find_checksum(&content, "compactc-x86_64.zip"),
+            Some(DIGEST.parse().unwrap())
+        );
+        assert_eq!(
+            find_checksum(&content, "compactc-aarch64.zip"),
+            Some(OTHER.parse().unwrap())
+        );
+        assert_eq!(find_checksum(&content, "compactc-arm.zip"), None);
+    }
+
+    #[test]
+    fn find_single_digest() {
+        assert_eq!(
+            find_checksum(&format!("{DIGEST}\n"), "compactc-x86_64.zip"),
+            Some(DIGEST.parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn digest_of_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("empty");
+        std::fs::write(&path, b"").unwrap();
+
+        let digest = Sha256Digest::of_file(&path).await.unwrap();
+
+        assert_eq!(digest.to_string(), OTHER);
+    }
+}
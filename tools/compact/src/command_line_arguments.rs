@@ -15,17 +15,39 @@
 
 use crate::{
     compact_directory::CompactDirectory,
+    config::{NetworkArguments, SourceArguments},
     console::{Icons, Style},
+    output::OutputFormat,
+    reporter::ColorChoice,
+    retention,
 };
 use anyhow::bail;
-use clap::{Args, Parser, Subcommand, ValueEnum};
-use semver::Version;
-use std::{fmt, str::FromStr};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum, builder::FalseyValueParser};
+use semver::{Version, VersionReq};
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+/// Options accepted by every command but hidden from their help, to keep it
+/// short: they are only listed in the long help of `compact` itself
+const HIDDEN_OPTIONS_HELP: &str = "\
+Release source, network and cache options, accepted by every command:
+      --api-url <URL>            GitHub API serving the releases [env: COMPACT_API_URL=]
+      --repository <OWNER/REPO>  Repository publishing the releases [env: COMPACT_REPOSITORY=]
+      --mirror <URL>             Mirror of the release assets, repeatable [env: COMPACT_MIRRORS=]
+      --registry <DIR>           Local registry serving the releases [env: COMPACT_REGISTRY=]
+      --proxy <URL>              Proxy for the HTTP and HTTPS requests [env: COMPACT_PROXY=]
+      --ca-bundle <FILE>         Certificate authorities to trust too [env: COMPACT_CA_BUNDLE=]
+      --connect-timeout <SECS>   Connection timeout [env: COMPACT_CONNECT_TIMEOUT=]
+      --read-timeout <SECS>      Timeout waiting for data [env: COMPACT_READ_TIMEOUT=]
+      --cache-dir <DIR>          Cache directory [env: COMPACT_CACHE_DIR=]
+      --cache-ttl <SECS>         Cache lifetime [env: COMPACT_CACHE_TTL=] [default: 900]
+      --lock-timeout <SECS>      Wait for a lock [env: COMPACT_LOCK_TIMEOUT=] [default: 600]";
 
 /// The Compact command-line tool provides a set of utilities for Compact smart
 /// contract development.
 #[derive(Debug, Clone, Parser)]
 #[clap(version)]
+#[command(after_long_help = HIDDEN_OPTIONS_HELP)]
 pub struct CommandLineArguments {
     /// Set the target
     ///
@@ -48,6 +70,84 @@ pub struct CommandLineArguments {
     )]
     pub directory: CompactDirectory,
 
+    /// Maximum time, in seconds, to wait for another compact process using
+    /// the compact artifact directory
+    #[arg(
+        long,
+        env = "COMPACT_LOCK_TIMEOUT",
+        global = true,
+        hide = true,
+        default_value_t = 600
+    )]
+    pub lock_timeout: u64,
+
+    /// Set the directory of the cached release information
+    ///
+    /// By default this is the `cache` directory of the compact artifact
+    /// directory.
+    #[arg(long, env = "COMPACT_CACHE_DIR", global = true, hide = true)]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Time, in seconds, the cached release information is used before being
+    /// refreshed
+    #[arg(
+        long,
+        env = "COMPACT_CACHE_TTL",
+        global = true,
+        hide = true,
+        default_value_t = 900
+    )]
+    pub cache_ttl: u64,
+
+    /// Never access the network
+    ///
+    /// Versions are resolved against the cached release information and the
+    /// installed versions only.
+    #[arg(
+        long,
+        env = "COMPACT_OFFLINE",
+        global = true,
+        value_parser = FalseyValueParser::new()
+    )]
+    pub offline: bool,
+
+    /// Format of the output
+    #[arg(value_enum, long, global = true, default_value_t)]
+    pub output: OutputFormat,
+
+    /// Only print errors, warnings and the requested data
+    ///
+    /// Progress and status messages are not printed.
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Print more information, repeat for more details
+    ///
+    /// `format` and `fixup` print every file they see. From `-vv`, diagnostic
+    /// traces are printed on the standard error, `COMPACT_LOG` selects them
+    /// too.
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+
+    /// When to colour the output
+    ///
+    /// With `auto`, the output is coloured when printed to a terminal and
+    /// the `NO_COLOR` environment variable is not set.
+    #[arg(
+        value_enum,
+        long,
+        env = "COMPACT_COLOR",
+        global = true,
+        default_value_t
+    )]
+    pub color: ColorChoice,
+
+    #[command(flatten)]
+    pub source: SourceArguments,
+
+    #[command(flatten)]
+    pub network: NetworkArguments,
+
     #[command(subcommand)]
     pub command: Command,
 
@@ -58,6 +158,33 @@ pub struct CommandLineArguments {
     pub icons: Icons,
 }
 
+impl CommandLineArguments {
+    /// Arguments using the given compact directory, with the defaults of the
+    /// other options and without waiting for locks
+    ///
+    /// This is the configuration of the toolchain proxies, whose command
+    /// line belongs to the program they forward to.
+    pub fn for_directory(directory: CompactDirectory) -> Self {
+        Self {
+            target: Target::default(),
+            directory,
+            lock_timeout: 0,
+            cache_dir: None,
+            cache_ttl: 0,
+            offline: false,
+            output: Default::default(),
+            quiet: false,
+            verbose: 0,
+            color: Default::default(),
+            source: Default::default(),
+            network: Default::default(),
+            command: Command::Compile(CompileCommand { args: Vec::new() }),
+            style: Default::default(),
+            icons: Default::default(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct CompactUpdateConfig {}
 
@@ -85,6 +212,27 @@ pub enum Command {
     )]
     Update(UpdateCommand),
 
+    #[command(
+        visible_alias = "un",
+        alias = "uni",
+        alias = "unin",
+        alias = "unins",
+        alias = "uninst",
+        alias = "uninsta",
+        alias = "uninstal"
+    )]
+    Uninstall(UninstallCommand),
+
+    #[command(
+        visible_alias = "use",
+        alias = "de",
+        alias = "def",
+        alias = "defa",
+        alias = "defau",
+        alias = "defaul"
+    )]
+    Default(DefaultCommand),
+
     #[command(
         visible_alias = "f",
         visible_alias = "fmt",
@@ -109,6 +257,11 @@ pub enum Command {
     #[command(visible_alias = "cl", alias = "cle", alias = "clea")]
     Clean(CleanCommand),
 
+    Changelog(ChangelogCommand),
+
+    #[command(alias = "do", alias = "doc", alias = "doct", alias = "docto")]
+    Doctor(DoctorCommand),
+
     #[command(
         name = "self",
         subcommand,
@@ -137,14 +290,22 @@ pub enum Command {
 }
 
 /// Check for updates with the remote server
+///
+/// Exits with 11 when no toolchain is installed and with 12 when the default
+/// toolchain doesn't match the version pinned by the project.
 #[derive(Debug, Clone, Args)]
 #[command(version)]
-pub struct CheckCommand {}
+pub struct CheckCommand {
+    /// Exit with 10 when a newer version is available
+    #[arg(long, default_value_t = false)]
+    pub fail_on_outdated: bool,
+}
 
 #[derive(Debug, Clone, Args)]
 #[command(version)]
 pub struct UpdateCommand {
-    /// Version to install, e.g. 0, 0.29, or 0.29.0
+    /// Version to install, e.g. 0.29.0, 0.29, ^0.29, ">=0.28, <0.31",
+    /// latest or stable (the default)
     #[arg(id = "COMPACT_VERSION")]
     pub version: Option<VersionSpec>,
 
@@ -152,30 +313,86 @@ pub struct UpdateCommand {
     #[arg(long, default_value_t = false)]
     pub no_set_default: bool,
 
+    /// Allow pre-release versions to be selected
+    #[arg(long, default_value_t = false)]
+    pub pre: bool,
+
+    /// Public key verifying the signatures of the artifacts, as a base64
+    /// minisign key or the path to a `minisign.pub` file. Defaults to the
+    /// key of the official releases
+    #[arg(long, env = "COMPACT_PUBLIC_KEY")]
+    pub public_key: Option<String>,
+
+    /// Install artifacts published without a signature
+    #[arg(long, default_value_t = false)]
+    pub allow_unsigned: bool,
+
     #[command(flatten)]
     pub config: CompactUpdateConfig,
 }
 
-/// A version specifier that is either an exact semver version, a
-/// `major.minor` prefix, or a `major`-only prefix.
+/// A version specifier: an exact semver version, a `major.minor` prefix, a
+/// `major`-only prefix, a semver requirement or a release channel.
+///
+/// Pre-release versions are only selected when asked for explicitly: with
+/// an exact version, the `latest` channel, a requirement naming a
+/// pre-release (e.g. `>=0.30.0-rc.1`), or when opted in with
+/// [`VersionSpec::matches_pre`].
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum VersionSpec {
     Exact(Version),
-    Partial { major: u64, minor: u64 },
-    Major { major: u64 },
+    Partial {
+        major: u64,
+        minor: u64,
+    },
+    Major {
+        major: u64,
+    },
+    /// A semver requirement, e.g. `^0.29`, `~0.29.1` or `>=0.28, <0.31`
+    Req(VersionReq),
+    /// The newest version, including pre-releases
+    Latest,
+    /// The newest version that is not a pre-release
+    Stable,
 }
 
 impl VersionSpec {
     /// Returns true when `version` matches this specifier.
     pub fn matches(&self, version: &Version) -> bool {
+        self.matches_pre(version, false)
+    }
+
+    /// Returns true when `version` matches this specifier, letting any
+    /// pre-release match when `allow_pre` is set.
+    pub fn matches_pre(&self, version: &Version, allow_pre: bool) -> bool {
+        let is_pre = !version.pre.is_empty();
+
+        // the release a pre-release leads to, used to match pre-releases
+        // against specifiers that only consider releases
+        let release = Version::new(version.major, version.minor, version.patch);
+
         match self {
             VersionSpec::Exact(v) => v == version,
+            VersionSpec::Latest => true,
+            VersionSpec::Stable => !is_pre,
+            VersionSpec::Req(req) => {
+                req.matches(version) || (allow_pre && is_pre && req.matches(&release))
+            }
             VersionSpec::Partial { major, minor } => {
-                version.major == *major && version.minor == *minor
+                (allow_pre || !is_pre) && version.major == *major && version.minor == *minor
             }
-            VersionSpec::Major { major } => version.major == *major,
+            VersionSpec::Major { major } => (allow_pre || !is_pre) && version.major == *major,
         }
     }
+
+    /// Select the newest version matching this specifier
+    pub fn select<'a>(
+        &self,
+        versions: impl DoubleEndedIterator<Item = &'a Version>,
+        allow_pre: bool,
+    ) -> Option<&'a Version> {
+        versions.rev().find(|v| self.matches_pre(v, allow_pre))
+    }
 }
 
 impl FromStr for VersionSpec {
@@ -186,6 +403,12 @@ impl FromStr for VersionSpec {
             return Ok(VersionSpec::Exact(version));
         }
 
+        match s {
+            "latest" => return Ok(VersionSpec::Latest),
+            "stable" => return Ok(VersionSpec::Stable),
+            _ => (),
+        }
+
         // Try parsing as "major.minor"
         let parts: Vec<&str> = s.split('.').collect();
         if parts.len() == 2
@@ -199,8 +422,12 @@ impl FromStr for VersionSpec {
             return Ok(VersionSpec::Major { major });
         }
 
-        // Fall back to semver error for the original input
-        Version::parse(s).map(VersionSpec::Exact)
+        // Try parsing as a semver requirement, and fall back to the semver
+        // error for the original input
+        match VersionReq::parse(s) {
+            Ok(req) if !s.trim().is_empty() => Ok(VersionSpec::Req(req)),
+            _ => Version::parse(s).map(VersionSpec::Exact),
+        }
     }
 }
 
@@ -214,6 +441,9 @@ impl fmt::Display for VersionSpec {
             VersionSpec::Major { major } => {
                 write!(f, "{major}")
             }
+            VersionSpec::Req(req) => req.fmt(f),
+            VersionSpec::Latest => "latest".fmt(f),
+            VersionSpec::Stable => "stable".fmt(f),
         }
     }
 }
@@ -229,10 +459,6 @@ pub struct FormatCommand {
     #[clap(short, long)]
     pub check: bool,
 
-    /// Print each file seen by the formatter
-    #[clap(short, long)]
-    pub verbose: bool,
-
     /// Print the toolchain version
     #[clap(short = 'V', long)]
     pub version: bool,
@@ -261,10 +487,6 @@ pub struct FixupCommand {
     #[clap(long)]
     pub vscode: bool,
 
-    /// Print verbose output
-    #[clap(short, long)]
-    pub verbose: bool,
-
     /// Print the toolchain version
     #[clap(short = 'V', long)]
     pub version: bool,
@@ -283,7 +505,75 @@ pub struct ListCommand {
     pub installed: bool,
 }
 
-/// Remove all compact versions
+/// Remove an installed version of the Compact toolchain
+///
+/// When the default version is removed, the newest remaining version becomes
+/// the default one.
+#[derive(Debug, Clone, Args)]
+#[command(version)]
+pub struct UninstallCommand {
+    /// Version to remove, e.g. 0.29.0 or 0.29. The newest installed version
+    /// matching it is removed.
+    #[arg(id = "COMPACT_VERSION")]
+    pub version: VersionSpec,
+
+    /// Target of the toolchain to remove, the current platform by default
+    #[arg(long, value_enum)]
+    pub target: Option<Target>,
+}
+
+/// Show or change the default version, among the installed ones
+///
+/// This never accesses the network: install new versions with `compact
+/// update`.
+#[derive(Debug, Clone, Args)]
+#[command(version)]
+pub struct DefaultCommand {
+    /// Version to make the default one, e.g. 0.29.0, 0.29 or ^0.29. The
+    /// newest installed version matching it is selected. Without it, the
+    /// default version is shown.
+    #[arg(id = "COMPACT_VERSION")]
+    pub version: Option<VersionSpec>,
+}
+
+/// Show the release notes of the compiler versions in a range
+#[derive(Debug, Clone, Args)]
+#[command(version)]
+pub struct ChangelogCommand {
+    /// Oldest version of the range, e.g. 0.28.0
+    pub from: Version,
+
+    /// Newest version of the range, the latest release by default
+    pub to: Option<Version>,
+
+    /// Include pre-release versions
+    #[arg(long, default_value_t = false)]
+    pub pre: bool,
+}
+
+/// Diagnose the installation of compact
+///
+/// Checks the compact directory is writable, the `bin` directory is in PATH
+/// before any other `compactc`, the links and proxies of `bin`, the programs
+/// of the installed toolchains, leftover downloads and the release cache.
+/// Exits with an error when problems other than warnings are found.
+#[derive(Debug, Clone, Args)]
+#[command(version)]
+pub struct DoctorCommand {
+    /// Repair the problems that can be: broken links and missing proxies in
+    /// `bin`, leftover downloads and corrupt cache files
+    #[arg(long, default_value_t = false)]
+    pub fix: bool,
+}
+
+/// Remove installed compact versions, except the pinned and kept ones
+///
+/// Without any retention policy, every installed version is removed. The
+/// policies, `--keep-latest`, `--keep`, `--older-than` and `--unused-since`,
+/// select versions to keep: a version selected by any of them is kept. The
+/// version pinned by the current project (`COMPACT_TOOLCHAIN` or
+/// `compact-toolchain.toml`) is never removed, nor is the default version with
+/// `--keep-current`. `--dry-run` lists what would be removed.
 #[derive(Debug, Clone, Args)]
 #[command(version)]
 pub struct CleanCommand {
@@ -294,6 +584,27 @@ pub struct CleanCommand {
     /// Also remove the cache directory
     #[arg(long, default_value_t = false)]
     pub cache: bool,
+
+    /// Keep the N newest versions
+    #[arg(long, value_name = "N")]
+    pub keep_latest: Option<usize>,
+
+    /// Keep the versions matching SPEC, can be repeated
+    #[arg(long, value_name = "SPEC")]
+    pub keep: Vec<VersionSpec>,
+
+    /// Keep the versions installed less than AGE ago, e.g. 30d
+    #[arg(long, value_name = "AGE", value_parser = retention::parse_age)]
+    pub older_than: Option<Duration>,
+
+    /// Keep the versions used less than AGE ago, e.g. 30d
+    #[arg(long, value_name = "AGE", value_parser = retention::parse_age)]
+    pub unused_since: Option<Duration>,
+
+    /// List what would be removed, and the space reclaimed, without removing
+    /// anything
+    #[arg(long, default_value_t = false)]
+    pub dry_run: bool,
 }
 
 /// Call the compiler
@@ -360,6 +671,19 @@ impl FromStr for Target {
     }
 }
 
+impl Serialize for Target {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Target {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let target = String::deserialize(deserializer)?;
+        target.parse().map_err(de::Error::custom)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -419,6 +743,87 @@ mod tests {
         assert_eq!(spec.to_string(), "29");
     }
 
+    #[test]
+    fn parse_requirements() {
+        for req in ["^0.29", "~0.29.1", ">=0.28, <0.31", "=0.29.0", "0.29.*"] {
+            let spec: VersionSpec = req.parse().unwrap();
+            assert_eq!(spec, VersionSpec::Req(VersionReq::parse(req).unwrap()));
+        }
+
+        let spec: VersionSpec = ">=0.28, <0.31".parse().unwrap();
+        assert_eq!(spec.to_string(), ">=0.28, <0.31");
+    }
+
+    #[test]
+    fn parse_channels() {
+        assert_eq!(
+            "latest".parse::<VersionSpec>().unwrap(),
+            VersionSpec::Latest
+        );
+        assert_eq!(
+            "stable".parse::<VersionSpec>().unwrap(),
+            VersionSpec::Stable
+        );
+        assert_eq!(VersionSpec::Latest.to_string(), "latest");
+        assert_eq!(VersionSpec::Stable.to_string(), "stable");
+    }
+
+    #[test]
+    fn requirement_matches() {
+        let spec: VersionSpec = ">=0.28, <0.31".parse().unwrap();
+        assert!(spec.matches(&Version::new(0, 28, 0)));
+        assert!(spec.matches(&Version::new(0, 30, 9)));
+        assert!(!spec.matches(&Version::new(0, 31, 0)));
+        assert!(!spec.matches(&Version::new(0, 27, 5)));
+
+        let spec: VersionSpec = "~0.29.1".parse().unwrap();
+        assert!(spec.matches(&Version::new(0, 29, 3)));
+        assert!(!spec.matches(&Version::new(0, 29, 0)));
+        assert!(!spec.matches(&Version::new(0, 30, 0)));
+    }
+
+    #[test]
+    fn pre_releases_are_opt_in() {
+        let pre = Version::parse("0.30.0-rc.1").unwrap();
+
+        assert!(!VersionSpec::Stable.matches(&pre));
+        assert!(!VersionSpec::Stable.matches_pre(&pre, true));
+        assert!(VersionSpec::Latest.matches(&pre));
+        assert!(VersionSpec::Exact(pre.clone()).matches(&pre));
+
+        let partial: VersionSpec = "0.30".parse().unwrap();
+        assert!(!partial.matches(&pre));
+        assert!(partial.matches_pre(&pre, true));
+
+        let req: VersionSpec = "^0.30".parse().unwrap();
+        assert!(!req.matches(&pre));
+        assert!(req.matches_pre(&pre, true));
+
+        let req: VersionSpec = ">=0.30.0-rc.1".parse().unwrap();
+        assert!(req.matches(&pre));
+    }
+
+    #[test]
+    fn select_newest_match() {
+        let versions: Vec<Version> = ["0.28.0", "0.29.0", "0.29.1", "0.30.0-rc.1"]
+            .iter()
+            .map(|v| v.parse().unwrap())
+            .collect();
+
+        let select = |spec: &str, pre| {
+            spec.parse::<VersionSpec>()
+                .unwrap()
+                .select(versions.iter(), pre)
+                .map(|v| v.to_string())
+        };
+
+        assert_eq!(select("stable", false).as_deref(), Some("0.29.1"));
+        assert_eq!(select("latest", false).as_deref(), Some("0.30.0-rc.1"));
+        assert_eq!(select("^0.29", false).as_deref(), Some("0.29.1"));
+        assert_eq!(select("0.30", false), None);
+        assert_eq!(select("0.30", true).as_deref(), Some("0.30.0-rc.1"));
+    }
+
     #[test]
     fn major_matches_any_minor_and_patch() {
         let spec = VersionSpec::Major { major: 1 };
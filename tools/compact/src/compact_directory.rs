@@ -22,6 +22,10 @@ use std::{
 
 const COMPACTUP_BIN_DIR: &str = "bin";
 pub const COMPACTUP_VERSIONS_DIR: &str = "versions";
+const COMPACTUP_SETTINGS_FILE: &str = "settings.toml";
+const COMPACTUP_STAGING_DIR: &str = "tmp";
+const COMPACTUP_CONFIG_FILE: &str = "config.toml";
+const COMPACTUP_CACHE_DIR: &str = "cache";
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct CompactDirectory(PathBuf);
@@ -34,6 +38,25 @@ impl CompactDirectory {
     pub fn versions_dir(&self) -> PathBuf {
         self.0.join(COMPACTUP_VERSIONS_DIR)
     }
+
+    pub fn settings_file(&self) -> PathBuf {
+        self.0.join(COMPACTUP_SETTINGS_FILE)
+    }
+
+    /// Directory where toolchains are prepared before being installed
+    pub fn staging_dir(&self) -> PathBuf {
+        self.0.join(COMPACTUP_STAGING_DIR)
+    }
+
+    /// Default directory of the cached release information
+    pub fn cache_dir(&self) -> PathBuf {
+        self.0.join(COMPACTUP_CACHE_DIR)
+    }
+
+    /// User configuration, see [`crate::config`]
+    pub fn config_file(&self) -> PathBuf {
+        self.0.join(COMPACTUP_CONFIG_FILE)
+    }
 }
 
 impl fmt::Display for CompactDirectory {
@@ -49,6 +72,12 @@ impl str::FromStr for CompactDirectory {
     }
 }
 
+impl From<PathBuf> for CompactDirectory {
+    fn from(path: PathBuf) -> Self {
+        Self(path)
+    }
+}
+
 impl Default for CompactDirectory {
     fn default() -> Self {
         std::env::home_dir()
@@ -13,15 +13,19 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::{CommandLineArguments, Target, compact_directory::COMPACTUP_VERSIONS_DIR};
-use anyhow::{Context, Result, anyhow, bail, ensure};
+use crate::{CommandLineArguments, Target, compact_directory::COMPACTUP_VERSIONS_DIR, utils};
+use anyhow::{Context, Result, bail, ensure};
 use semver::Version;
 use std::{
     ffi::OsStr,
     path::{Path, PathBuf},
     process::Stdio,
 };
-use tokio::{fs, process::Command};
+use tokio::process::Command;
+
+/// File of a toolchain directory recording when the toolchain was last used,
+/// as a number of seconds since the Unix epoch
+pub const LAST_USED_FILE: &str = ".last-used";
 
 pub struct Compiler {
     version: Version,
@@ -55,31 +59,6 @@ impl Compiler {
         self.dir.join("fixup-compact")
     }
 
-    pub async fn create(
-        cfg: &CommandLineArguments,
-        version: Version,
-        target: Target,
-    ) -> Result<Self> {
-        let dir = cfg
-            .directory
-            .versions_dir()
-            .join(version.to_string())
-            .join(target.to_string());
-
-        let bin = dir.join("compactc");
-
-        fs::create_dir_all(&dir)
-            .await
-            .with_context(|| anyhow!("Failed to create directory `{dir:?}'"))?;
-
-        Ok(Self {
-            version,
-            target,
-            dir,
-            bin,
-        })
-    }
-
     pub async fn open(
         cfg: &CommandLineArguments,
         version: Version,
@@ -112,6 +91,18 @@ impl Compiler {
         self.target
     }
 
+    /// Record that the toolchain is being used, for `compact clean
+    /// --unused-since`. This is best effort: a read-only directory is not an
+    /// error.
+    pub fn mark_used(&self) {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        let _ = std::fs::write(self.dir.join(LAST_USED_FILE), now.to_string());
+    }
+
     pub async fn invoke<I, S>(&self, args: I) -> Result<()>
     where
         I: IntoIterator<Item = S>,
@@ -120,6 +111,8 @@ impl Compiler {
         let program = self.path_compactc();
         let cwd = ".";
 
+        self.mark_used();
+
         let mut cmd = Command::new(program);
 
         // execute the unzip command in the artifact directory
@@ -136,12 +129,13 @@ impl Compiler {
         // inherit the sandard input
         cmd.stdin(Stdio::inherit());
 
+        let start = utils::trace_spawn(cmd.as_std());
         let mut child = cmd.spawn().context("Failed to spawn compactc command")?;
 
-        let status = child
-            .wait()
-            .await
-            .context("Failed to execute the compactc command")?;
+        let status = child.wait().await;
+        utils::trace_exit(cmd.as_std(), start, status.as_ref().copied());
+
+        let status = status.context("Failed to execute the compactc command")?;
         if !status.success() {
             if let Some(code) = status.code() {
                 // It was requested that the behaviour of the compact tool
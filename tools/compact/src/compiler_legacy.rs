@@ -13,20 +13,48 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use anyhow::{Context, Result, anyhow};
+use crate::{
+    archive::Unzip,
+    checksum::{self, Sha256Digest},
+    config::ReleaseSource,
+    fetch::ReleaseAsset,
+    http, signature,
+};
+use anyhow::{Context, Result, anyhow, bail};
+use minisign_verify::PublicKey;
 use reqwest::Url;
 use semver::Version;
-use std::{path::PathBuf, process::Stdio};
-use tokio::process::Command;
+use std::path::PathBuf;
 
 pub struct CompilerAsset {
     pub path: PathBuf,
-    pub asset: octocrab::models::repos::Asset,
+    pub asset: ReleaseAsset,
+    pub checksums: Vec<ReleaseAsset>,
+    pub signatures: Vec<ReleaseAsset>,
     pub version: Version,
+    pub source: ReleaseSource,
+}
+
+/// Outcome of the verification of a downloaded asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verification {
+    /// The digest matched the one published in `source`
+    Verified { source: String },
+    /// The release publishes no digest for the asset
+    NoDigest,
+}
+
+/// Outcome of the verification of the signature of a downloaded asset
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureCheck {
+    /// The signature is valid, with its trusted comment
+    Verified { trusted_comment: String },
+    /// The release publishes no signature for the asset
+    Unsigned,
 }
 
 impl CompilerAsset {
-    fn path_zip(&self) -> PathBuf {
+    pub fn path_zip(&self) -> PathBuf {
         self.path.join("artifact.zip")
     }
     fn path_compactc(&self) -> PathBuf {
@@ -37,44 +65,324 @@ impl CompilerAsset {
         self.path_compactc().is_file()
     }
 
-    pub fn download_url(&self) -> &Url {
-        &self.asset.browser_download_url
+    /// The URLs the archive can be downloaded from, in order of preference
+    pub fn download_urls(&self) -> Vec<Url> {
+        self.source.asset_urls(&self.asset.browser_download_url)
+    }
+
+    /// Look up the expected digest of the asset
+    ///
+    /// The checksums files published with the release are preferred, the
+    /// digest reported in the release metadata is used otherwise.
+    pub async fn expected_digest(
+        &self,
+        client: &http::Client,
+    ) -> Result<Option<(Sha256Digest, String)>> {
+        // a checksums file dedicated to the asset first
+        let mut candidates: Vec<_> = self.checksums.iter().collect();
+        candidates.sort_by_key(|checksums| !checksums.name.starts_with(&self.asset.name));
+
+        for checksums in candidates {
+            let content = client
+                .get_text_from(&self.source.asset_urls(&checksums.browser_download_url))
+                .await
+                .with_context(|| anyhow!("Failed to download checksums `{}'", checksums.name))?;
+
+            if let Some(digest) = checksum::find_checksum(&content, &self.asset.name) {
+                return Ok(Some((digest, checksums.name.clone())));
+            }
+        }
+
+        match &self.asset.digest {
+            Some(digest) if digest.starts_with("sha256:") => {
+                let digest = digest.parse().with_context(|| {
+                    anyhow!("Invalid digest in the metadata of `{}'", self.asset.name)
+                })?;
+
+                Ok(Some((digest, "release metadata".to_owned())))
+            }
+            _ => Ok(None),
+        }
     }
 
-    pub async fn unzip(&self) -> Result<()> {
-        let cwd = &self.path;
+    /// Verify the downloaded archive against the published digest
+    ///
+    /// On mismatch the archive is removed so the next attempt downloads it
+    /// again.
+    pub async fn verify(&self, client: &http::Client) -> Result<Verification> {
+        let Some((expected, source)) = self.expected_digest(client).await? else {
+            return Ok(Verification::NoDigest);
+        };
+
+        let zip = self.path_zip();
+        let actual = Sha256Digest::of_file(&zip).await?;
+
+        if actual != expected {
+            tokio::fs::remove_file(&zip)
+                .await
+                .with_context(|| anyhow!("Failed to remove corrupted download `{zip:?}'"))?;
+
+            bail!(
+                "Checksum mismatch for `{name}': expected {expected} (from {source}), got {actual}. The download was removed",
+                name = self.asset.name,
+            );
+        }
 
-        let mut cmd = Command::new("unzip");
+        Ok(Verification::Verified { source })
+    }
 
-        // execute the unzip command in the artifact directory
-        cmd.current_dir(cwd);
-        cmd.arg(self.path_zip());
+    /// Verify the downloaded archive against its detached signature
+    ///
+    /// On an invalid signature the archive is removed so the next attempt
+    /// downloads it again.
+    pub async fn verify_signature(
+        &self,
+        client: &http::Client,
+        public_key: &PublicKey,
+    ) -> Result<SignatureCheck> {
+        let name = format!("{}{}", self.asset.name, signature::SIGNATURE_EXTENSION);
 
-        // capture the StdOut and StdErr
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
+        let Some(signature_asset) = self.signatures.iter().find(|asset| asset.name == name) else {
+            return Ok(SignatureCheck::Unsigned);
+        };
 
-        // don't allow StdIn, we don't have anything to pass in the standard
-        // input and we don't want it to be inherited
-        cmd.stdin(Stdio::null());
+        let content = client
+            .get_text_from(
+                &self
+                    .source
+                    .asset_urls(&signature_asset.browser_download_url),
+            )
+            .await
+            .with_context(|| anyhow!("Failed to download signature `{name}'"))?;
 
-        let child = cmd
-            .spawn()
-            .context("Failed to spawn artifact extraction command")?;
+        let zip = self.path_zip();
 
-        let output = child
-            .wait_with_output()
+        let verified = signature::verify_file(&zip, &content, public_key)
             .await
-            .context("Failed to execute the artifact extraction command")?;
-        let status = output.status;
-        if !status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr);
-            Err(anyhow!("Stderr: {stderr}"))
-                .with_context(|| anyhow!("Status: {status}"))
-                .with_context(|| anyhow!("Command=unzip CWD={cwd:?}"))
-                .context("artifact Extraction failed")
-        } else {
-            Ok(())
+            .and_then(|trusted_comment| {
+                signature::check_signed_file(&trusted_comment, &self.asset.name)?;
+                Ok(trusted_comment)
+            });
+
+        match verified {
+            Ok(trusted_comment) => Ok(SignatureCheck::Verified { trusted_comment }),
+            Err(error) => {
+                tokio::fs::remove_file(&zip)
+                    .await
+                    .with_context(|| anyhow!("Failed to remove corrupted download `{zip:?}'"))?;
+
+                Err(error).with_context(|| {
+                    anyhow!(
+                        "Invalid signature for `{name}'. The download was removed",
+                        name = self.asset.name
+                    )
+                })
+            }
         }
     }
+
+    /// Prepare the extraction of the downloaded archive in the asset
+    /// directory
+    pub fn unzip(&self) -> Result<Unzip> {
+        Unzip::new(self.path_zip(), &self.path).context("artifact Extraction failed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    const ASSET: &str = "compactc_v0.29.0_x86_64-unknown-linux-musl.zip";
+    // SHA-256 of an empty file
+    const EMPTY: &str = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+    const OTHER: &str = "3a7bd3e2360a3d29eea436fcfb7e44c735d117c42d1c1835420b6b9942dd4f1b";
+
+    fn release_asset(name: &str, url: &str, digest: Option<&str>) -> ReleaseAsset {
+        ReleaseAsset {
+            name: name.to_owned(),
+            browser_download_url: url.parse().unwrap(),
+            size: 0,
+            digest: digest.map(str::to_owned),
+        }
+    }
+
+    async fn compiler_asset(
+        dir: &tempfile::TempDir,
+        digest: Option<&str>,
+        checksums: Vec<ReleaseAsset>,
+    ) -> CompilerAsset {
+        std::fs::write(dir.path().join("artifact.zip"), b"").unwrap();
+
+        CompilerAsset {
+            path: dir.path().to_path_buf(),
+            asset: release_asset(ASSET, "https://example.com/artifact.zip", digest),
+            checksums,
+            signatures: Vec::new(),
+            version: Version::new(0, 29, 0),
+            source: ReleaseSource::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_with_checksums_file() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/SHA256SUMS"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(format!("{EMPTY}  {ASSET}\n")))
+            .mount(&mock_server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let checksums = release_asset(
+            "SHA256SUMS",
+            &format!("{}/SHA256SUMS", mock_server.uri()),
+            None,
+        );
+        // the checksums file takes precedence over the metadata
+        let asset = compiler_asset(&dir, Some(&format!("sha256:{OTHER}")), vec![checksums]).await;
+
+        let verification = asset.verify(&http::Client::new().unwrap()).await.unwrap();
+
+        assert_eq!(
+            verification,
+            Verification::Verified {
+                source: "SHA256SUMS".to_owned()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_with_metadata() {
+        let dir = tempfile::tempdir().unwrap();
+        let asset = compiler_asset(&dir, Some(&format!("sha256:{EMPTY}")), vec![]).await;
+
+        let verification = asset.verify(&http::Client::new().unwrap()).await.unwrap();
+
+        assert_eq!(
+            verification,
+            Verification::Verified {
+                source: "release metadata".to_owned()
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_without_digest() {
+        let dir = tempfile::tempdir().unwrap();
+        let asset = compiler_asset(&dir, None, vec![]).await;
+
+        let verification = asset.verify(&http::Client::new().unwrap()).await.unwrap();
+
+        assert_eq!(verification, Verification::NoDigest);
+    }
+
+    #[tokio::test]
+    async fn mismatch_removes_download() {
+        let dir = tempfile::tempdir().unwrap();
+        let asset = compiler_asset(&dir, Some(&format!("sha256:{OTHER}")), vec![]).await;
+
+        let error = asset
+            .verify(&http::Client::new().unwrap())
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("Checksum mismatch"));
+        assert!(!dir.path().join("artifact.zip").exists());
+    }
+
+    async fn signed_asset(dir: &tempfile::TempDir, mock_server: &MockServer) -> CompilerAsset {
+        let mut asset = compiler_asset(dir, None, vec![]).await;
+        asset.signatures = vec![release_asset(
+            &format!("{ASSET}.minisig"),
+            &format!("{}/signature", mock_server.uri()),
+            None,
+        )];
+        asset
+    }
+
+    async fn serve_signature(mock_server: &MockServer, file: &str) {
+        let signature = signature::tests::sign(signature::tests::CONTENT, file);
+
+        Mock::given(method("GET"))
+            .and(path("/signature"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(signature))
+            .mount(mock_server)
+            .await;
+    }
+
+    #[tokio::test]
+    async fn valid_signature() {
+        let mock_server = MockServer::start().await;
+        serve_signature(&mock_server, ASSET).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let asset = signed_asset(&dir, &mock_server).await;
+        std::fs::write(asset.path_zip(), signature::tests::CONTENT).unwrap();
+
+        let key = signature::parse_public_key(&signature::tests::public_key()).unwrap();
+        let check = asset
+            .verify_signature(&http::Client::new().unwrap(), &key)
+            .await
+            .unwrap();
+
+        assert!(matches!(check, SignatureCheck::Verified { .. }));
+    }
+
+    #[tokio::test]
+    async fn invalid_signature_removes_download() {
+        let mock_server = MockServer::start().await;
+        serve_signature(&mock_server, ASSET).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let asset = signed_asset(&dir, &mock_server).await;
+
+        let key = signature::parse_public_key(&signature::tests::public_key()).unwrap();
+        let error = asset
+            .verify_signature(&http::Client::new().unwrap(), &key)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("Invalid signature"));
+        assert!(!asset.path_zip().exists());
+    }
+
+    #[tokio::test]
+    async fn signature_of_another_asset() {
+        let mock_server = MockServer::start().await;
+        serve_signature(
+            &mock_server,
+            "compactc_v0.28.0_x86_64-unknown-linux-musl.zip",
+        )
+        .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let asset = signed_asset(&dir, &mock_server).await;
+        std::fs::write(asset.path_zip(), signature::tests::CONTENT).unwrap();
+
+        let key = signature::parse_public_key(&signature::tests::public_key()).unwrap();
+        let error = asset
+            .verify_signature(&http::Client::new().unwrap(), &key)
+            .await
+            .unwrap_err();
+
+        assert!(error.to_string().starts_with("Invalid signature"));
+        assert!(!asset.path_zip().exists());
+    }
+
+    #[tokio::test]
+    async fn unsigned_asset() {
+        let dir = tempfile::tempdir().unwrap();
+        let asset = compiler_asset(&dir, None, vec![]).await;
+
+        let key = signature::parse_public_key(&signature::tests::public_key()).unwrap();
+        let check = asset
+            .verify_signature(&http::Client::new().unwrap(), &key)
+            .await
+            .unwrap();
+
+        assert_eq!(check, SignatureCheck::Unsigned);
+    }
 }
@@ -0,0 +1,471 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Configuration of the release source and of the network access
+//!
+//! Releases are fetched from `midnightntwrk/compact` on GitHub by default.
+//! The source can be changed with command line options, environment
+//! variables, or the `config.toml` file of the compact directory:
+//!
+//! ```toml
+//! [source]
+//! api-url = "https://github.example.com/api/v3"
+//! repository = "midnightntwrk/compact"
+//! mirrors = ["https://mirror.example.com/compact"]
+//! ```
+//!
+//! Mirrors serve the release assets at `<mirror>/<release tag>/<asset name>`
+//! and are tried in order when downloading from the release fails.
+//!
+//! A local `registry` replaces GitHub altogether, see [`crate::registry`].
+//!
+//! The `[network]` section configures the HTTP client, e.g. behind a
+//! TLS-intercepting proxy:
+//!
+//! ```toml
+//! [network]
+//! proxy = "http://proxy.example.com:3128"
+//! ca-bundle = "/etc/ssl/certs/corporate.pem"
+//! connect-timeout = 30
+//! read-timeout = 60
+//! ```
+
+use crate::{CommandLineArguments, compact_directory::CompactDirectory, registry};
+use anyhow::{Context, Result, anyhow, bail};
+use clap::Args;
+use reqwest::Url;
+use serde::Deserialize;
+use std::{fmt, io::ErrorKind, path::PathBuf, str::FromStr, time::Duration};
+
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+pub const DEFAULT_OWNER: &str = "midnightntwrk";
+pub const DEFAULT_REPO: &str = "compact";
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 30;
+pub const DEFAULT_READ_TIMEOUT: u64 = 60;
+
+/// Command line options of the release source
+///
+/// These, and the network options, are hidden from the help of the commands
+/// and listed in the long help of `compact` only.
+#[derive(Debug, Clone, Default, Args)]
+pub struct SourceArguments {
+    /// URL of the GitHub API serving the releases, e.g. of a GitHub Enterprise
+    /// server
+    #[arg(long, env = "COMPACT_API_URL", global = true, hide = true)]
+    pub api_url: Option<Url>,
+
+    /// Repository publishing the releases, as `owner/repo`
+    #[arg(long, env = "COMPACT_REPOSITORY", global = true, hide = true)]
+    pub repository: Option<Repository>,
+
+    /// Mirror of the release assets, tried in order when a download from the
+    /// release fails
+    #[arg(
+        long = "mirror",
+        env = "COMPACT_MIRRORS",
+        global = true,
+        hide = true,
+        value_delimiter = ','
+    )]
+    pub mirrors: Vec<Url>,
+
+    /// Local directory, or `file://` URL, serving the releases instead of
+    /// GitHub
+    #[arg(long, env = "COMPACT_REGISTRY", global = true, hide = true, value_parser = registry::parse_registry)]
+    pub registry: Option<Url>,
+}
+
+/// Command line options of the network access
+#[derive(Debug, Clone, Default, Args)]
+pub struct NetworkArguments {
+    /// Proxy for the HTTP and HTTPS requests, instead of the one of the
+    /// `HTTPS_PROXY`, `HTTP_PROXY` or `ALL_PROXY` environment variables.
+    /// `NO_PROXY` still applies.
+    #[arg(long, env = "COMPACT_PROXY", global = true, hide = true)]
+    pub proxy: Option<Url>,
+
+    /// PEM file of certificate authorities to trust in addition to the
+    /// built-in ones, e.g. of a TLS-intercepting proxy
+    #[arg(long, env = "COMPACT_CA_BUNDLE", global = true, hide = true)]
+    pub ca_bundle: Option<PathBuf>,
+
+    /// Maximum time, in seconds, to connect to a server
+    #[arg(long, env = "COMPACT_CONNECT_TIMEOUT", global = true, hide = true)]
+    pub connect_timeout: Option<u64>,
+
+    /// Maximum time, in seconds, to wait for data from a server
+    #[arg(long, env = "COMPACT_READ_TIMEOUT", global = true, hide = true)]
+    pub read_timeout: Option<u64>,
+}
+
+/// A GitHub repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Repository {
+    pub owner: String,
+    pub repo: String,
+}
+
+/// Where the releases are fetched from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseSource {
+    pub api_url: Url,
+    pub repository: Repository,
+    pub mirrors: Vec<Url>,
+    pub registry: Option<Url>,
+}
+
+/// Settings of the HTTP client
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkSettings {
+    pub proxy: Option<Url>,
+    pub ca_bundle: Option<PathBuf>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+}
+
+/// Content of the `config.toml` file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    source: SourceSection,
+    #[serde(default)]
+    network: NetworkSection,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct NetworkSection {
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    connect_timeout: Option<u64>,
+    read_timeout: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+struct SourceSection {
+    api_url: Option<String>,
+    repository: Option<String>,
+    #[serde(default)]
+    mirrors: Vec<String>,
+    registry: Option<String>,
+}
+
+impl ReleaseSource {
+    /// Resolve the release source: the command line options and environment
+    /// variables first, then the configuration file, then the defaults.
+    pub async fn load(cfg: &CommandLineArguments) -> Result<Self> {
+        let file = load_config_file(&cfg.directory).await?;
+
+        Self::resolve(&cfg.source, file.source)
+    }
+
+    fn resolve(arguments: &SourceArguments, file: SourceSection) -> Result<Self> {
+        let api_url = match (&arguments.api_url, file.api_url) {
+            (Some(url), _) => url.clone(),
+            (None, Some(url)) => parse_url(&url)?,
+            (None, None) => ReleaseSource::default().api_url,
+        };
+
+        let repository = match (&arguments.repository, file.repository) {
+            (Some(repository), _) => repository.clone(),
+            (None, Some(repository)) => repository.parse()?,
+            (None, None) => Repository::default(),
+        };
+
+        let mirrors = if arguments.mirrors.is_empty() {
+            file.mirrors
+                .iter()
+                .map(|mirror| parse_url(mirror))
+                .collect::<Result<_>>()?
+        } else {
+            arguments.mirrors.clone()
+        };
+
+        let registry = match (&arguments.registry, file.registry) {
+            (Some(registry), _) => Some(registry.clone()),
+            (None, Some(path)) => Some(registry::parse_registry(&path)?),
+            (None, None) => None,
+        };
+
+        Ok(Self {
+            api_url,
+            repository,
+            mirrors,
+            registry,
+        })
+    }
+
+    /// Identify the source of cached release information
+    pub fn cache_key(&self) -> String {
+        format!("{} {}", self.api_url, self.repository)
+    }
+
+    /// The URLs an asset can be downloaded from, in order: the release then
+    /// the mirrors
+    pub fn asset_urls(&self, url: &Url) -> Vec<Url> {
+        let mut urls = vec![url.clone()];
+
+        // `.../releases/download/<tag>/<name>`
+        let mut segments = url.path_segments().into_iter().flatten().rev();
+        if let (Some(name), Some(tag)) = (segments.next(), segments.next()) {
+            for mirror in &self.mirrors {
+                let mut base = mirror.clone();
+                if !base.path().ends_with('/') {
+                    base.set_path(&format!("{}/", base.path()));
+                }
+
+                if let Ok(url) = base.join(&format!("{tag}/{name}")) {
+                    urls.push(url);
+                }
+            }
+        }
+
+        urls
+    }
+}
+
+impl Default for ReleaseSource {
+    fn default() -> Self {
+        Self {
+            api_url: Url::parse(DEFAULT_API_URL).expect("the default API URL is valid"),
+            repository: Repository::default(),
+            mirrors: Vec::new(),
+            registry: None,
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Resolve the network settings: the command line options and
+    /// environment variables first, then the configuration file, then the
+    /// defaults.
+    pub async fn load(cfg: &CommandLineArguments) -> Result<Self> {
+        let file = load_config_file(&cfg.directory).await?;
+
+        Self::resolve(&cfg.network, file.network)
+    }
+
+    fn resolve(arguments: &NetworkArguments, file: NetworkSection) -> Result<Self> {
+        let proxy = match (&arguments.proxy, file.proxy) {
+            (Some(proxy), _) => Some(proxy.clone()),
+            (None, Some(proxy)) => Some(parse_url(&proxy)?),
+            (None, None) => None,
+        };
+
+        let seconds = |argument: Option<u64>, file: Option<u64>, default| {
+            Duration::from_secs(argument.or(file).unwrap_or(default))
+        };
+
+        Ok(Self {
+            proxy,
+            ca_bundle: arguments.ca_bundle.clone().or(file.ca_bundle),
+            connect_timeout: seconds(
+                arguments.connect_timeout,
+                file.connect_timeout,
+                DEFAULT_CONNECT_TIMEOUT,
+            ),
+            read_timeout: seconds(
+                arguments.read_timeout,
+                file.read_timeout,
+                DEFAULT_READ_TIMEOUT,
+            ),
+        })
+    }
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            proxy: None,
+            ca_bundle: None,
+            connect_timeout: Duration::from_secs(DEFAULT_CONNECT_TIMEOUT),
+            read_timeout: Duration::from_secs(DEFAULT_READ_TIMEOUT),
+        }
+    }
+}
+
+async fn load_config_file(directory: &CompactDirectory) -> Result<ConfigFile> {
+    let path = directory.config_file();
+
+    let content = match tokio::fs::read_to_string(&path).await {
+        Ok(content) => content,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(ConfigFile::default()),
+        reason => reason.with_context(|| anyhow!("Failed to read config `{path:?}'"))?,
+    };
+
+    toml::from_str(&content).with_context(|| anyhow!("Invalid config file `{path:?}'"))
+}
+
+fn parse_url(url: &str) -> Result<Url> {
+    Url::parse(url).with_context(|| anyhow!("Invalid URL `{url}'"))
+}
+
+impl Default for Repository {
+    fn default() -> Self {
+        Self {
+            owner: DEFAULT_OWNER.to_owned(),
+            repo: DEFAULT_REPO.to_owned(),
+        }
+    }
+}
+
+impl FromStr for Repository {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once('/') {
+            Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') => {
+                Ok(Self {
+                    owner: owner.to_owned(),
+                    repo: repo.to_owned(),
+                })
+            }
+            _ => bail!("Invalid repository `{s}', expected `owner/repo'"),
+        }
+    }
+}
+
+impl fmt::Display for Repository {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(url: &str) -> Url {
+        url.parse().unwrap()
+    }
+
+    #[test]
+    fn defaults() {
+        let source =
+            ReleaseSource::resolve(&SourceArguments::default(), SourceSection::default()).unwrap();
+
+        assert_eq!(source.api_url, url(DEFAULT_API_URL));
+        assert_eq!(source.repository.to_string(), "midnightntwrk/compact");
+        assert!(source.mirrors.is_empty());
+        assert!(source.registry.is_none());
+    }
+
+    #[test]
+    fn arguments_take_precedence_over_file() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [source]
+            api-url = "https://github.example.com/api/v3"
+            repository = "example/compact"
+            mirrors = ["https://mirror.example.com/compact"]
+            registry = "/srv/compact"
+            "#,
+        )
+        .unwrap();
+
+        let arguments = SourceArguments {
+            api_url: None,
+            repository: Some("fork/compact".parse().unwrap()),
+            mirrors: Vec::new(),
+            registry: None,
+        };
+
+        let source = ReleaseSource::resolve(&arguments, file.source).unwrap();
+
+        assert_eq!(source.api_url, url("https://github.example.com/api/v3"));
+        assert_eq!(source.repository.to_string(), "fork/compact");
+        assert_eq!(
+            source.mirrors,
+            vec![url("https://mirror.example.com/compact")]
+        );
+    }
+
+    #[test]
+    fn invalid_file() {
+        assert!(toml::from_str::<ConfigFile>("[source]\nurl = \"x\"").is_err());
+
+        let file: ConfigFile = toml::from_str("[source]\nrepository = \"compact\"").unwrap();
+        assert!(ReleaseSource::resolve(&SourceArguments::default(), file.source).is_err());
+    }
+
+    #[test]
+    fn network_settings() {
+        let file: ConfigFile = toml::from_str(
+            r#"
+            [network]
+            proxy = "http://proxy.example.com:3128"
+            ca-bundle = "/etc/ssl/certs/corporate.pem"
+            read-timeout = 120
+            "#,
+        )
+        .unwrap();
+
+        let arguments = NetworkArguments {
+            read_timeout: Some(5),
+            ..Default::default()
+        };
+
+        let settings = NetworkSettings::resolve(&arguments, file.network).unwrap();
+
+        assert_eq!(settings.proxy, Some(url("http://proxy.example.com:3128")));
+        assert_eq!(
+            settings.ca_bundle,
+            Some(PathBuf::from("/etc/ssl/certs/corporate.pem"))
+        );
+        assert_eq!(
+            settings.connect_timeout,
+            Duration::from_secs(DEFAULT_CONNECT_TIMEOUT)
+        );
+        assert_eq!(settings.read_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn parse_repository() {
+        let repository: Repository = "midnightntwrk/compact".parse().unwrap();
+        assert_eq!(repository, Repository::default());
+
+        assert!("compact".parse::<Repository>().is_err());
+        assert!("/compact".parse::<Repository>().is_err());
+        assert!("a/b/c".parse::<Repository>().is_err());
+    }
+
+    #[test]
+    fn mirror_urls() {
+        let source = ReleaseSource {
+            api_url: url(DEFAULT_API_URL),
+            repository: Repository::default(),
+            mirrors: vec![
+                url("https://mirror.example.com/compact"),
+                url("https://backup.example.com/"),
+            ],
+            registry: None,
+        };
+
+        let asset = url(
+            "https://github.com/midnightntwrk/compact/releases/download/compactc-v0.29.0/compactc.zip",
+        );
+
+        assert_eq!(
+            source.asset_urls(&asset),
+            vec![
+                asset.clone(),
+                url("https://mirror.example.com/compact/compactc-v0.29.0/compactc.zip"),
+                url("https://backup.example.com/compactc-v0.29.0/compactc.zip"),
+            ]
+        );
+    }
+}
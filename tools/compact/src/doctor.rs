@@ -0,0 +1,509 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Diagnosis of the installation of compact
+//!
+//! `compact doctor` runs the checks of this module. With `--fix`, it repairs
+//! what can be repaired without downloading anything: the broken links and
+//! the missing proxies of the `bin` directory, the leftover downloads and the
+//! corrupt release cache files.
+
+use std::{
+    ffi::OsString,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, anyhow};
+use indicatif::HumanBytes;
+use semver::Version;
+use tokio::fs;
+
+use crate::{
+    CommandLineArguments, fetch, install,
+    lock::{INSTALL_LOCK, Lock, SETTINGS_LOCK},
+    output::{Diagnosis, DiagnosisStatus, DoctorReport},
+    proxy::{self, Proxy},
+    settings::Settings,
+    utils,
+};
+
+const FIX_HINT: &str = "run `compact doctor --fix'";
+
+/// Run every check, repairing the problems found when `fix` is set
+pub async fn diagnose(cfg: &CommandLineArguments, fix: bool) -> Result<DoctorReport> {
+    let directory = check_directory(cfg, fix).await;
+
+    // nothing can be repaired in a read-only directory
+    let fix = fix && directory.status == DiagnosisStatus::Pass;
+    let _lock = match fix {
+        true => Some(Lock::directory(cfg, INSTALL_LOCK).await?),
+        false => None,
+    };
+
+    let checks = vec![
+        directory,
+        check_path(cfg, std::env::var_os("PATH")),
+        check_bin(cfg, fix).await?,
+        check_toolchains(cfg).await?,
+        check_leftovers(cfg, fix).await?,
+        check_cache(cfg, fix).await?,
+    ];
+
+    Ok(DoctorReport { checks })
+}
+
+/// The diagnosis of `check`, with the status `severity` when `problems`
+/// were found and not `fixed`
+fn diagnosis(
+    check: &'static str,
+    severity: DiagnosisStatus,
+    problems: Vec<String>,
+    fixed: bool,
+    pass: impl Into<String>,
+    hint: impl Into<String>,
+) -> Diagnosis {
+    let count = match problems.len() {
+        1 => "1 problem".to_owned(),
+        count => format!("{count} problems"),
+    };
+
+    let (status, summary, hint) = match () {
+        _ if problems.is_empty() => (DiagnosisStatus::Pass, pass.into(), None),
+        _ if fixed => (DiagnosisStatus::Fixed, format!("{count} fixed"), None),
+        _ => (severity, format!("{count} found"), Some(hint.into())),
+    };
+
+    Diagnosis {
+        check,
+        status,
+        summary,
+        problems,
+        hint,
+    }
+}
+
+/// Check the compact directory is writable, or can be created
+///
+/// The directory is only created when fixing: otherwise, a missing directory
+/// is checked through its closest existing ancestor.
+async fn check_directory(cfg: &CommandLineArguments, fix: bool) -> Diagnosis {
+    let directory = &cfg.directory;
+    let path = directory.to_path_buf();
+
+    let (writable, pass) = if fix || path.is_dir() {
+        let writable = async {
+            fs::create_dir_all(&path).await?;
+            probe_writable(&path).await
+        }
+        .await;
+
+        (writable, format!("`{directory}' is writable"))
+    } else {
+        let ancestor = path
+            .ancestors()
+            .find(|ancestor| ancestor.is_dir())
+            .unwrap_or(Path::new("."));
+
+        (
+            probe_writable(ancestor).await,
+            format!("`{directory}' doesn't exist yet and can be created"),
+        )
+    };
+
+    let problems = match writable {
+        Ok(()) => Vec::new(),
+        Err(error) => vec![format!("`{directory}' is not writable: {error}")],
+    };
+
+    diagnosis(
+        "directory",
+        DiagnosisStatus::Fail,
+        problems,
+        false,
+        pass,
+        "fix its permissions, or set COMPACT_DIRECTORY to a writable directory",
+    )
+}
+
+async fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    let probe = dir.join(format!(".doctor-{}", std::process::id()));
+
+    fs::write(&probe, b"").await?;
+    fs::remove_file(&probe).await
+}
+
+/// Check the `bin` directory is in `path`, before any other directory
+/// providing `compactc`
+fn check_path(cfg: &CommandLineArguments, path: Option<OsString>) -> Diagnosis {
+    let bin = cfg.directory.bin_dir();
+    let compactc = format!("compactc{}", std::env::consts::EXE_SUFFIX);
+
+    let mut problems = Vec::new();
+    let mut found = false;
+
+    for dir in std::env::split_paths(&path.unwrap_or_default()) {
+        if dir.as_os_str().is_empty() {
+            continue;
+        }
+
+        if same_path(&dir, &bin) {
+            found = true;
+            break;
+        }
+
+        let program = dir.join(&compactc);
+        if program.is_file() {
+            problems.push(format!(
+                "`{}' comes before `{}' in PATH",
+                program.display(),
+                bin.display()
+            ));
+        }
+    }
+
+    if !found {
+        problems.push(format!("`{}' is not in PATH", bin.display()));
+    }
+
+    diagnosis(
+        "path",
+        DiagnosisStatus::Fail,
+        problems,
+        false,
+        format!("`{}' is in PATH", bin.display()),
+        format!("add `{}' at the beginning of PATH", bin.display()),
+    )
+}
+
+fn same_path(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (std::fs::canonicalize(a), std::fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// Check the symbolic links of the `bin` directory resolve within the
+/// compact directory, and that the proxies are installed when a default
+/// toolchain is set
+async fn check_bin(cfg: &CommandLineArguments, fix: bool) -> Result<Diagnosis> {
+    let bin = cfg.directory.bin_dir();
+    let directory =
+        std::fs::canonicalize(&*cfg.directory).unwrap_or_else(|_| cfg.directory.to_path_buf());
+
+    let mut problems = Vec::new();
+    let mut broken = Vec::new();
+
+    for path in entries(&bin).await? {
+        // not a symbolic link
+        let Ok(target) = fs::read_link(&path).await else {
+            continue;
+        };
+
+        match fs::canonicalize(bin.join(&target)).await {
+            Err(_) => problems.push(format!(
+                "`{}' is a dangling link to `{}'",
+                path.display(),
+                target.display()
+            )),
+            Ok(resolved) if !resolved.starts_with(&directory) => problems.push(format!(
+                "`{}' links to `{}', outside of the compact directory",
+                path.display(),
+                target.display()
+            )),
+            Ok(_) => continue,
+        }
+
+        broken.push(path);
+    }
+
+    let default = Settings::load(&cfg.directory).await?.default;
+
+    if default.is_some() {
+        for proxy in Proxy::ALL {
+            let path = bin.join(proxy.file_name());
+
+            if !path.is_file() && !broken.contains(&path) {
+                problems.push(format!("the `{}' proxy is missing", proxy.name()));
+            }
+        }
+    }
+
+    let fixed = fix && !problems.is_empty();
+
+    if fixed {
+        for path in &broken {
+            fs::remove_file(path)
+                .await
+                .with_context(|| anyhow!("Failed to remove `{}'", path.display()))?;
+        }
+
+        if default.is_some() {
+            let _lock = Lock::directory(cfg, SETTINGS_LOCK).await?;
+            proxy::install_proxies(cfg).await?;
+        }
+    }
+
+    Ok(diagnosis(
+        "bin",
+        DiagnosisStatus::Fail,
+        problems,
+        fixed,
+        format!("the links and proxies of `{}' are valid", bin.display()),
+        FIX_HINT,
+    ))
+}
+
+/// Check the installed toolchains contain their programs
+async fn check_toolchains(cfg: &CommandLineArguments) -> Result<Diagnosis> {
+    let mut problems = Vec::new();
+
+    for (version, dir) in toolchain_dirs(cfg).await? {
+        let target = dir.file_name().unwrap_or_default().to_string_lossy();
+
+        for problem in install::program_problems(&dir, &version)? {
+            problems.push(format!("{version} ({target}): {problem}"));
+        }
+    }
+
+    Ok(diagnosis(
+        "toolchains",
+        DiagnosisStatus::Fail,
+        problems,
+        false,
+        "every installed toolchain is complete",
+        "remove the toolchain from the `versions' directory and run `compact update VERSION'",
+    ))
+}
+
+/// Check for downloads left by interrupted installations, or by older
+/// versions of compact in the installed toolchains
+async fn check_leftovers(cfg: &CommandLineArguments, fix: bool) -> Result<Diagnosis> {
+    let staging = cfg.directory.staging_dir();
+    let mut leftovers = entries(&staging).await?;
+
+    for (_, dir) in toolchain_dirs(cfg).await? {
+        leftovers.extend(
+            install::DOWNLOAD_FILES
+                .iter()
+                .map(|name| dir.join(name))
+                .filter(|path| path.is_file()),
+        );
+    }
+
+    let mut problems = Vec::new();
+
+    for path in &leftovers {
+        let size = utils::disk_usage(path).await?;
+        problems.push(format!("`{}' ({})", path.display(), HumanBytes(size)));
+    }
+
+    let fixed = fix && !leftovers.is_empty();
+
+    if fixed {
+        install::clean_staging(cfg, None).await?;
+
+        for path in leftovers.iter().filter(|path| !path.starts_with(&staging)) {
+            utils::remove_file_if_exists(path).await?;
+        }
+    }
+
+    Ok(diagnosis(
+        "leftovers",
+        DiagnosisStatus::Warn,
+        problems,
+        fixed,
+        "no leftover download",
+        FIX_HINT,
+    ))
+}
+
+/// Check the release cache files can be read
+async fn check_cache(cfg: &CommandLineArguments, fix: bool) -> Result<Diagnosis> {
+    let corrupt = fetch::corrupt_cache_files(cfg).await?;
+
+    let problems = corrupt
+        .iter()
+        .map(|path| format!("`{}' is corrupt", path.display()))
+        .collect::<Vec<_>>();
+
+    let fixed = fix && !corrupt.is_empty();
+
+    if fixed {
+        for path in &corrupt {
+            utils::remove_file_if_exists(path).await?;
+        }
+    }
+
+    Ok(diagnosis(
+        "cache",
+        DiagnosisStatus::Warn,
+        problems,
+        fixed,
+        "the release cache is readable",
+        FIX_HINT,
+    ))
+}
+
+/// The directories of the installed toolchains, with their version
+async fn toolchain_dirs(cfg: &CommandLineArguments) -> Result<Vec<(Version, PathBuf)>> {
+    let mut toolchains = Vec::new();
+
+    for dir in entries(&cfg.directory.versions_dir()).await? {
+        let Some(version) = dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.parse::<Version>().ok())
+        else {
+            continue;
+        };
+
+        for target in entries(&dir).await? {
+            if target.is_dir() {
+                toolchains.push((version.clone(), target));
+            }
+        }
+    }
+
+    toolchains.sort();
+
+    Ok(toolchains)
+}
+
+/// The entries of `dir`, none when it doesn't exist
+async fn entries(dir: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = match fs::read_dir(dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => {
+            return Err(error).with_context(|| anyhow!("Failed to read directory `{dir:?}'"));
+        }
+    };
+
+    let mut paths = Vec::new();
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| anyhow!("Failed to read directory `{dir:?}'"))?
+    {
+        paths.push(entry.path());
+    }
+
+    paths.sort();
+
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompactDirectory;
+
+    fn cfg(dir: &Path) -> CommandLineArguments {
+        CommandLineArguments::for_directory(CompactDirectory::from(dir.to_path_buf()))
+    }
+
+    #[test]
+    fn diagnosis_status() {
+        let check = |problems: &[&str], fixed| {
+            let problems = problems.iter().map(|p| p.to_string()).collect();
+            let diagnosis = diagnosis("test", DiagnosisStatus::Warn, problems, fixed, "ok", "hint");
+            (diagnosis.status, diagnosis.summary, diagnosis.hint)
+        };
+
+        assert_eq!(
+            check(&[], true),
+            (DiagnosisStatus::Pass, "ok".to_owned(), None)
+        );
+        assert_eq!(
+            check(&["a"], false),
+            (
+                DiagnosisStatus::Warn,
+                "1 problem found".to_owned(),
+                Some("hint".to_owned())
+            )
+        );
+        assert_eq!(
+            check(&["a", "b"], true),
+            (DiagnosisStatus::Fixed, "2 problems fixed".to_owned(), None)
+        );
+    }
+
+    #[test]
+    fn bin_dir_in_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(&dir.path().join("compact"));
+        let bin = cfg.directory.bin_dir();
+
+        // another directory providing compactc
+        let other = dir.path().join("other");
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(other.join("compactc"), b"").unwrap();
+
+        let path = |dirs: &[&Path]| Some(std::env::join_paths(dirs).unwrap());
+
+        let diagnosis = check_path(&cfg, path(&[&bin, &other]));
+        assert_eq!(diagnosis.status, DiagnosisStatus::Pass);
+
+        let diagnosis = check_path(&cfg, path(&[&other, &bin]));
+        assert_eq!(diagnosis.status, DiagnosisStatus::Fail);
+        assert_eq!(
+            diagnosis.problems,
+            [format!(
+                "`{}' comes before `{}' in PATH",
+                other.join("compactc").display(),
+                bin.display()
+            )]
+        );
+
+        let diagnosis = check_path(&cfg, None);
+        assert_eq!(
+            diagnosis.problems,
+            [format!("`{}' is not in PATH", bin.display())]
+        );
+    }
+
+    #[tokio::test]
+    async fn remove_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path());
+        let version = Version::new(0, 29, 0);
+
+        let staging = install::staging_dir(&cfg, &version, cfg.target);
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join("artifact.zip.partial"), b"zip").unwrap();
+
+        let toolchain = cfg.directory.versions_dir().join("0.29.0").join("target");
+        std::fs::create_dir_all(&toolchain).unwrap();
+        std::fs::write(toolchain.join("artifact.zip"), b"zip").unwrap();
+        std::fs::write(toolchain.join("compactc"), b"").unwrap();
+
+        let diagnosis = check_leftovers(&cfg, false).await.unwrap();
+        assert_eq!(diagnosis.status, DiagnosisStatus::Warn);
+        assert_eq!(diagnosis.problems.len(), 2);
+        assert!(staging.exists());
+
+        let diagnosis = check_leftovers(&cfg, true).await.unwrap();
+        assert_eq!(diagnosis.status, DiagnosisStatus::Fixed);
+        assert!(!staging.exists());
+        assert!(!toolchain.join("artifact.zip").exists());
+        assert!(toolchain.join("compactc").exists());
+
+        let diagnosis = check_leftovers(&cfg, false).await.unwrap();
+        assert_eq!(diagnosis.status, DiagnosisStatus::Pass);
+    }
+}
@@ -14,16 +14,24 @@
 // limitations under the License.
 
 use crate::{
-    CommandLineArguments, Target, compact_directory::COMPACTUP_VERSIONS_DIR,
+    CommandLineArguments, Target, checksum,
     compiler_legacy::CompilerAsset,
+    config::{ReleaseSource, Repository},
+    http,
+    lock::Lock,
+    registry, signature, utils,
 };
 use anyhow::{Context, Result, anyhow};
-use octocrab::models::repos::Asset;
+use reqwest::{
+    StatusCode, Url,
+    header::{ACCEPT, AUTHORIZATION, ETAG, HeaderMap, HeaderValue, IF_NONE_MATCH},
+};
 use semver::Version;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
-use std::path::PathBuf;
+use sha2::{Digest as _, Sha256};
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{collections::BTreeMap, fmt};
 use tokio::time::sleep;
 
 #[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
@@ -33,73 +41,214 @@ pub struct MidnightArtifacts {
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 struct CachedResponse {
+    /// Format of the cache, see [`CACHE_VERSION`]
+    pub version: u32,
+    /// Release source the response comes from, see
+    /// [`ReleaseSource::cache_key`]
+    pub source: String,
     pub artifacts: MidnightArtifacts,
     pub cached_at: u64,
     pub rate_limit_remaining: Option<u32>,
     pub rate_limit_reset: Option<u64>,
+    /// ETag of the first page of releases
+    #[serde(default)]
+    pub etag: Option<String>,
 }
 
-const CACHE_TTL_SECONDS: u64 = 900; // 15 minutes
+/// Version of the cache format, caches of another version are discarded
+const CACHE_VERSION: u32 = 3;
+const CACHE_FILE_PREFIX: &str = "releases-";
 const MAX_RETRIES: u32 = 3;
+const RELEASES_PER_PAGE: u32 = 100; // maximum allowed by GitHub
+const MAX_RELEASE_PAGES: u32 = 50;
 const RETRY_BASE_DELAY_MS: u64 = 1000; // 1 second
+const GITHUB_API_VERSION: &str = "2022-11-28";
 
 #[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
 pub struct MidnightCompiler {
     pub version: Version,
-    pub x86_macos: Option<Asset>,
-    pub aarch64_macos: Option<Asset>,
-    pub x86_linux: Option<Asset>,
-    pub aarch64_linux: Option<Asset>,
+    pub x86_macos: Option<ReleaseAsset>,
+    pub aarch64_macos: Option<ReleaseAsset>,
+    pub x86_linux: Option<ReleaseAsset>,
+    pub aarch64_linux: Option<ReleaseAsset>,
+    /// Checksums files published with the release
+    #[serde(default)]
+    pub checksums: Vec<ReleaseAsset>,
+    /// Detached signatures published with the release
+    #[serde(default)]
+    pub signatures: Vec<ReleaseAsset>,
+    /// Release notes, the body of the GitHub release
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+/// An asset of a release, as described by the GitHub releases API
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ReleaseAsset {
+    pub name: String,
+    pub browser_download_url: Url,
+    pub size: u64,
+    /// Digest computed by GitHub when the asset was uploaded, e.g.
+    /// `sha256:<hex>`. Assets uploaded before GitHub started reporting
+    /// digests don't have one.
+    #[serde(default)]
+    pub digest: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct Release {
+    pub(crate) tag_name: String,
+    #[serde(default)]
+    pub(crate) body: Option<String>,
+    #[serde(default)]
+    pub(crate) assets: Vec<ReleaseAsset>,
 }
 
 impl MidnightArtifacts {
-    pub async fn load() -> Result<Self> {
+    pub async fn load(cfg: &CommandLineArguments) -> Result<Self> {
+        let source = ReleaseSource::load(cfg).await?;
+
+        // a local registry is read directly, it is neither cached nor
+        // subject to the offline mode
+        if let Some(registry) = &source.registry {
+            tracing::debug!(%registry, "loading the releases of the local registry");
+            let compilers = registry::load(registry).await?;
+            return Ok(Self { compilers });
+        }
+
+        let cache = Cache::new(cfg, &source)?;
+
+        // a single process queries GitHub and updates the cache at a time,
+        // the others wait for the fresh cache
+        let _lock = Lock::acquire(cfg, cache.lock_path()).await?;
+
+        if cfg.offline {
+            return Self::load_offline(cfg, &cache).await;
+        }
+
         // Try to load from cache first
-        if let Ok(cached) = load_from_cache().await {
-            if is_cache_valid(&cached) {
-                return Ok(cached.artifacts);
+        let cached = cache.load().await;
+
+        if let Some(cached) = &cached {
+            if cache.is_valid(cached) {
+                tracing::debug!(path = %cache.path.display(), "release cache hit");
+                return Ok(cached.artifacts.clone());
             }
 
             // Check if we're approaching rate limits and should use cache even if stale
-            if should_use_cache_for_rate_limiting(&cached) {
-                return Ok(cached.artifacts);
+            if should_use_cache_for_rate_limiting(cached) {
+                tracing::debug!(path = %cache.path.display(), "release cache hit, stale");
+                return Ok(cached.artifacts.clone());
             }
         }
 
-        // Cache miss or expired, fetch from GitHub with retry logic
-        let octocrab_builder = octocrab::OctocrabBuilder::new();
-
-        let octocrab = if let Ok(token) = std::env::var("GITHUB_TOKEN") {
-            octocrab_builder.personal_token(token).build()?
-        } else {
-            octocrab_builder.build()?
+        tracing::debug!(
+            path = %cache.path.display(),
+            stale = cached.is_some(),
+            "release cache miss"
+        );
+
+        // Cache miss or expired, fetch from GitHub with retry logic. The
+        // stale cache is revalidated with its ETag, a 304 response doesn't
+        // count against the rate limit
+        let github = GitHub::new(
+            http::Client::load(cfg).await?,
+            source.api_url.clone(),
+            std::env::var("GITHUB_TOKEN").ok(),
+        );
+        let etag = cached.as_ref().and_then(|cached| cached.etag.clone());
+
+        let (releases, rate_limit) =
+            match load_compiler_versions_with_retry(&github, &source.repository, etag.as_deref())
+                .await
+            {
+                Ok(result) => result,
+                Err(error) if error.downcast_ref::<RateLimitExceeded>().is_some() => {
+                    let Some(cached) = cached else {
+                        return Err(error).context("Failed to load the compiler artifacts");
+                    };
+
+                    eprintln!("Warning: {error:#}, using cached release information");
+                    return Ok(cached.artifacts);
+                }
+                Err(error) => return Err(error).context("Failed to load the compiler artifacts"),
+            };
+
+        let (artifacts, etag) = match releases {
+            Releases::NotModified => {
+                tracing::debug!("release cache revalidated");
+                let cached = cached.context("Unexpected `304 Not Modified' without cache")?;
+                (cached.artifacts, etag)
+            }
+            Releases::Modified { compilers, etag } => (Self { compilers }, etag),
         };
 
-        let (compilers, rate_info) = load_compiler_versions_with_retry(octocrab)
-            .await
-            .context("Failed to load the compiler artifacts")?;
-
-        let artifacts = Self { compilers };
-
         // Cache the response
         let cached_response = CachedResponse {
+            version: CACHE_VERSION,
+            source: cache.source.clone(),
             artifacts: artifacts.clone(),
             cached_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
-            rate_limit_remaining: rate_info.0,
-            rate_limit_reset: rate_info.1,
+            rate_limit_remaining: rate_limit.remaining,
+            rate_limit_reset: rate_limit.reset,
+            etag,
         };
 
-        if let Err(e) = save_to_cache(&cached_response).await {
+        if let Err(e) = cache.save(&cached_response).await {
             // Don't fail the operation if caching fails, just log it
             eprintln!("Warning: Failed to cache GitHub API response: {e}");
         }
 
         Ok(artifacts)
     }
+
+    /// Resolve the artifacts without accessing the network: the cached
+    /// release information, whatever its age, completed with the installed
+    /// versions
+    async fn load_offline(cfg: &CommandLineArguments, cache: &Cache) -> Result<Self> {
+        let cached = cache.load().await;
+        tracing::debug!(
+            path = %cache.path.display(),
+            hit = cached.is_some(),
+            "offline, using the release cache"
+        );
+
+        let mut artifacts = cached.map(|cached| cached.artifacts).unwrap_or_default();
+
+        for version in utils::installed_versions(cfg).await? {
+            artifacts
+                .compilers
+                .entry(version.clone())
+                .or_insert_with(|| MidnightCompiler::installed(version));
+        }
+
+        Ok(artifacts)
+    }
 }
 
 impl MidnightCompiler {
-    pub fn compiler(&self, cfg: &CommandLineArguments) -> Result<CompilerAsset> {
+    /// A version known only from its installation, without release assets
+    fn installed(version: Version) -> Self {
+        Self {
+            version,
+            x86_macos: None,
+            aarch64_macos: None,
+            x86_linux: None,
+            aarch64_linux: None,
+            checksums: Vec::new(),
+            signatures: Vec::new(),
+            notes: None,
+        }
+    }
+
+    /// The asset of the compiler for the target of `cfg`, downloaded from
+    /// `source` and installed in `path`
+    pub fn compiler(
+        &self,
+        cfg: &CommandLineArguments,
+        source: ReleaseSource,
+        path: PathBuf,
+    ) -> Result<CompilerAsset> {
         let asset = match cfg.target {
             Target::x86_64UnknownLinuxMusl => self.x86_linux.clone(),
             Target::Aarch64UnknownLinuxMusl => self.aarch64_linux.clone(),
@@ -110,37 +259,150 @@ impl MidnightCompiler {
         let asset =
             asset.with_context(|| anyhow!("Unsupported compiler platform: {}", cfg.target))?;
 
-        let path = cfg
-            .directory
-            .join(COMPACTUP_VERSIONS_DIR)
-            .join(self.version.to_string())
-            .join(cfg.target.to_string());
-
         let compiler_asset = CompilerAsset {
             path,
             asset,
+            checksums: self.checksums.clone(),
+            signatures: self.signatures.clone(),
             version: self.version.clone(),
+            source,
         };
 
         Ok(compiler_asset)
     }
 }
 
+/// Client of the GitHub releases API
+struct GitHub {
+    client: http::Client,
+    api_url: Url,
+    token: Option<String>,
+}
+
+/// Rate limit reported by the `X-RateLimit-*` headers of the GitHub API
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RateLimit {
+    remaining: Option<u32>,
+    /// Unix time at which the rate limit resets
+    reset: Option<u64>,
+}
+
+/// The releases fetched from GitHub
+#[derive(Debug)]
+enum Releases {
+    /// The releases didn't change since the given ETag
+    NotModified,
+    Modified {
+        compilers: BTreeMap<Version, MidnightCompiler>,
+        etag: Option<String>,
+    },
+}
+
+/// The GitHub API rate limit is exhausted, retrying before the reset is
+/// pointless
+#[derive(Debug)]
+struct RateLimitExceeded {
+    reset: Option<u64>,
+}
+
+impl GitHub {
+    fn new(client: http::Client, api_url: Url, token: Option<String>) -> Self {
+        Self {
+            client,
+            api_url,
+            token,
+        }
+    }
+
+    fn releases_url(&self, repository: &Repository, page: u32) -> Result<Url> {
+        let mut url = self.api_url.clone();
+
+        url.path_segments_mut()
+            .map_err(|()| anyhow!("Invalid API URL `{}'", self.api_url))?
+            .pop_if_empty()
+            .extend(["repos", &repository.owner, &repository.repo, "releases"]);
+
+        url.query_pairs_mut()
+            .append_pair("per_page", &RELEASES_PER_PAGE.to_string())
+            .append_pair("page", &page.to_string());
+
+        Ok(url)
+    }
+
+    async fn get(&self, url: Url, etag: Option<&str>) -> Result<reqwest::Response> {
+        let mut headers = HeaderMap::new();
+
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+        headers.insert(
+            "X-GitHub-Api-Version",
+            HeaderValue::from_static(GITHUB_API_VERSION),
+        );
+
+        if let Some(token) = &self.token {
+            let value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .context("Invalid `GITHUB_TOKEN'")?;
+            headers.insert(AUTHORIZATION, value);
+        }
+
+        if let Some(etag) = etag {
+            headers.insert(IF_NONE_MATCH, HeaderValue::from_str(etag)?);
+        }
+
+        self.client.get(url, headers).await
+    }
+}
+
+impl RateLimit {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let header = |name: &str| headers.get(name)?.to_str().ok()?.parse().ok();
+
+        Self {
+            remaining: header("x-ratelimit-remaining").map(|remaining: u64| remaining as u32),
+            reset: header("x-ratelimit-reset"),
+        }
+    }
+}
+
+impl fmt::Display for RateLimitExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "GitHub API rate limit exceeded")?;
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        if let Some(reset) = self.reset {
+            write!(
+                f,
+                ", it resets in {} minute(s)",
+                reset.saturating_sub(now).div_ceil(60)
+            )?;
+        }
+
+        write!(f, ". Set `GITHUB_TOKEN' to raise the limit")
+    }
+}
+
+impl std::error::Error for RateLimitExceeded {}
+
 async fn load_compiler_versions_with_retry(
-    octocrab: octocrab::Octocrab,
-) -> Result<(
-    BTreeMap<Version, MidnightCompiler>,
-    (Option<u32>, Option<u64>),
-)> {
+    github: &GitHub,
+    repository: &Repository,
+    etag: Option<&str>,
+) -> Result<(Releases, RateLimit)> {
     let mut last_error = None;
 
     for attempt in 0..MAX_RETRIES {
-        match load_compiler_versions(&octocrab).await {
-            Ok((compilers, rate_info)) => return Ok((compilers, rate_info)),
+        match load_compiler_versions(github, repository, etag).await {
+            Ok(result) => return Ok(result),
+            Err(e) if e.downcast_ref::<RateLimitExceeded>().is_some() => return Err(e),
             Err(e) => {
                 last_error = Some(e);
 
-                // Check if this is a rate limit error
                 if attempt < MAX_RETRIES - 1 {
                     let delay = RETRY_BASE_DELAY_MS * (2_u64.pow(attempt));
 
@@ -160,24 +422,74 @@ async fn load_compiler_versions_with_retry(
     Err(last_error.unwrap())
 }
 
+/// Fetch the releases, the first page being requested conditionally with
+/// `etag`
+///
+/// The first page lists the newest releases, it changes whenever a release
+/// is published or its assets are updated.
 async fn load_compiler_versions(
-    octocrab: &octocrab::Octocrab,
-) -> Result<(
-    BTreeMap<Version, MidnightCompiler>,
-    (Option<u32>, Option<u64>),
-)> {
-    let releases = octocrab
-        .repos("midnightntwrk", "compact")
-        .releases()
-        .list()
-        .send()
+    github: &GitHub,
+    repository: &Repository,
+    etag: Option<&str>,
+) -> Result<(Releases, RateLimit)> {
+    let mut releases: Vec<Release> = Vec::new();
+    let mut rate_limit = RateLimit::default();
+    let mut new_etag = None;
+
+    // every page is fetched so older versions remain available; a short
+    // page is the last one
+    for page in 1..=MAX_RELEASE_PAGES {
+        let url = github.releases_url(repository, page)?;
+        let response = github
+            .get(url, etag.filter(|_| page == 1))
+            .await
+            .with_context(|| anyhow!("Error while fetching compact releases"))?;
+
+        rate_limit = RateLimit::from_headers(response.headers());
+        tracing::trace!(page, remaining = ?rate_limit.remaining, "GitHub rate limit");
+
+        if response.status() == StatusCode::NOT_MODIFIED {
+            return Ok((Releases::NotModified, rate_limit));
+        }
+
+        if matches!(
+            response.status(),
+            StatusCode::FORBIDDEN | StatusCode::TOO_MANY_REQUESTS
+        ) && rate_limit.remaining == Some(0)
+        {
+            return Err(RateLimitExceeded {
+                reset: rate_limit.reset,
+            })
+            .with_context(|| anyhow!("Error while fetching compact releases"));
+        }
+
+        if page == 1 {
+            new_etag = response
+                .headers()
+                .get(ETAG)
+                .and_then(|etag| etag.to_str().ok())
+                .map(str::to_owned);
+        }
+
+        // the releases are read as raw JSON for the asset digests
+        let page_releases: Vec<Release> = async {
+            response
+                .error_for_status()
+                .context("HTTP Get request returned error")?
+                .json()
+                .await
+                .context("Failed to decode the JSON response body")
+        }
         .await
         .with_context(|| anyhow!("Error while fetching compact releases"))?;
 
-    // For now, we can't easily access rate limit headers from octocrab
-    // We'll implement basic caching and retry logic without rate limit monitoring
-    let rate_limit_remaining = None;
-    let rate_limit_reset = None;
+        let last_page = page_releases.len() < RELEASES_PER_PAGE as usize;
+        releases.extend(page_releases);
+
+        if last_page {
+            break;
+        }
+    }
 
     let mut output = BTreeMap::new();
 
@@ -188,10 +500,16 @@ async fn load_compiler_versions(
         }
     }
 
-    Ok((output, (rate_limit_remaining, rate_limit_reset)))
+    Ok((
+        Releases::Modified {
+            compilers: output,
+            etag: new_etag,
+        },
+        rate_limit,
+    ))
 }
 
-async fn load_compiler_version(dir: octocrab::models::repos::Release) -> Result<MidnightCompiler> {
+pub(crate) async fn load_compiler_version(dir: Release) -> Result<MidnightCompiler> {
     let version = dir
         .tag_name
         .strip_prefix("compactc-v")
@@ -203,9 +521,17 @@ async fn load_compiler_version(dir: octocrab::models::repos::Release) -> Result<
     let mut aarch64_macos = None;
     let mut x86_linux = None;
     let mut aarch64_linux = None;
+    let mut checksums = Vec::new();
+    let mut signatures = Vec::new();
 
     for asset in dir.assets {
-        if asset.name.contains("aarch64-darwin") {
+        // checked first, checksums and signature files are usually named
+        // after the asset
+        if checksum::is_checksums_file(&asset.name) {
+            checksums.push(asset);
+        } else if signature::is_signature_file(&asset.name) {
+            signatures.push(asset);
+        } else if asset.name.contains("aarch64-darwin") {
             aarch64_macos = Some(asset);
         } else if asset.name.contains("x86_64-apple-darwin") || asset.name.contains("x86_64-darwin")
         {
@@ -225,58 +551,199 @@ async fn load_compiler_version(dir: octocrab::models::repos::Release) -> Result<
         aarch64_macos,
         x86_linux,
         aarch64_linux,
+        checksums,
+        signatures,
+        notes: dir.body.filter(|body| !body.trim().is_empty()),
     })
 }
 
-pub fn get_cache_path() -> Result<PathBuf> {
-    let cache_dir = dirs::cache_dir()
-        .or_else(|| std::env::temp_dir().into())
-        .ok_or_else(|| anyhow!("Unable to determine cache directory"))?;
+/// Directory of the cached release information: `--cache-dir`, or the
+/// `cache` directory of the compact directory
+pub fn cache_dir(cfg: &CommandLineArguments) -> PathBuf {
+    cfg.cache_dir
+        .clone()
+        .unwrap_or_else(|| cfg.directory.cache_dir())
+}
 
-    let cache_path = cache_dir.join("compactc").join("github_cache.json");
+/// Remove the cached release information of every source, and the cache of
+/// the earlier versions of compact, returning the removed files
+pub async fn clear_cache(cfg: &CommandLineArguments) -> Result<Vec<PathBuf>> {
+    let files = removable_cache_files(cfg).await?;
 
-    // Ensure cache directory exists
-    if let Some(parent) = cache_path.parent() {
-        std::fs::create_dir_all(parent).context("Failed to create cache directory")?;
+    for path in &files {
+        utils::remove_file_if_exists(path).await?;
     }
 
-    Ok(cache_path)
+    Ok(files)
 }
 
-async fn load_from_cache() -> Result<CachedResponse> {
-    let cache_path = get_cache_path()?;
+/// List the release cache files that can't be read. Cache files written by
+/// other versions of compact are not corrupt, they are discarded when loaded
+pub async fn corrupt_cache_files(cfg: &CommandLineArguments) -> Result<Vec<PathBuf>> {
+    let mut corrupt = Vec::new();
+
+    for path in cache_files(cfg).await? {
+        let readable = tokio::fs::read_to_string(&path)
+            .await
+            .is_ok_and(|contents| is_readable_cache(&contents));
 
-    if !cache_path.exists() {
-        return Err(anyhow!("Cache file does not exist"));
+        if !readable {
+            corrupt.push(path);
+        }
     }
 
-    let contents = tokio::fs::read_to_string(&cache_path)
-        .await
-        .context("Failed to read cache file")?;
+    Ok(corrupt)
+}
+
+/// The cache files `clear_cache` removes: the cache files of the sources, and
+/// the cache of the earlier versions of compact if it is still there
+pub async fn removable_cache_files(cfg: &CommandLineArguments) -> Result<Vec<PathBuf>> {
+    let mut files = cache_files(cfg).await?;
 
-    serde_json::from_str(&contents).context("Failed to deserialize cache file")
+    files.extend(legacy_cache_file().filter(|path| path.is_file()));
+
+    Ok(files)
 }
 
-async fn save_to_cache(cached_response: &CachedResponse) -> Result<()> {
-    let cache_path = get_cache_path()?;
+/// Cache of the GitHub releases written by the versions of compact predating
+/// the per source caches, in the cache directory of the user
+fn legacy_cache_file() -> Option<PathBuf> {
+    let non_empty = |name| std::env::var_os(name).filter(|value| !value.is_empty());
+
+    let cache_dir = if cfg!(windows) {
+        PathBuf::from(non_empty("LOCALAPPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(non_empty("HOME")?)
+            .join("Library")
+            .join("Caches")
+    } else {
+        non_empty("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .or_else(|| Some(PathBuf::from(non_empty("HOME")?).join(".cache")))?
+    };
+
+    Some(cache_dir.join("compactc").join("github_cache.json"))
+}
 
-    let contents =
-        serde_json::to_string_pretty(cached_response).context("Failed to serialize cache data")?;
+fn is_readable_cache(contents: &str) -> bool {
+    let Ok(value) = serde_json::from_str::<serde_json::Value>(contents) else {
+        return false;
+    };
 
-    tokio::fs::write(&cache_path, contents)
-        .await
-        .context("Failed to write cache file")?;
+    match value.get("version").and_then(serde_json::Value::as_u64) {
+        Some(version) if version == u64::from(CACHE_VERSION) => {
+            serde_json::from_value::<CachedResponse>(value).is_ok()
+        }
+        _ => value.is_object(),
+    }
+}
+
+/// List the release cache files
+pub async fn cache_files(cfg: &CommandLineArguments) -> Result<Vec<PathBuf>> {
+    let dir = cache_dir(cfg);
+    let mut files = Vec::new();
+
+    let mut entries = match tokio::fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(files),
+        Err(error) => {
+            return Err(error).with_context(|| anyhow!("Failed to read cache directory `{dir:?}'"));
+        }
+    };
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_cache = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with(CACHE_FILE_PREFIX) && name.ends_with(".json"));
+
+        if is_cache {
+            files.push(path);
+        }
+    }
+
+    Ok(files)
+}
 
-    Ok(())
+/// Cached release information of one release source
+struct Cache {
+    path: PathBuf,
+    source: String,
+    ttl: u64,
 }
 
-fn is_cache_valid(cached: &CachedResponse) -> bool {
-    let now = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|d| d.as_secs())
-        .unwrap_or(0);
+impl Cache {
+    fn new(cfg: &CommandLineArguments, source: &ReleaseSource) -> Result<Self> {
+        Self::in_dir(&cache_dir(cfg), source, cfg.cache_ttl)
+    }
+
+    fn in_dir(dir: &Path, source: &ReleaseSource, ttl: u64) -> Result<Self> {
+        std::fs::create_dir_all(dir)
+            .with_context(|| anyhow!("Failed to create cache directory `{dir:?}'"))?;
+
+        let key = source.cache_key();
+        let digest = Sha256::digest(key.as_bytes());
+        let path = dir.join(format!(
+            "{CACHE_FILE_PREFIX}{}.json",
+            hex::encode(&digest[..8])
+        ));
+
+        Ok(Self {
+            path,
+            source: key,
+            ttl,
+        })
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        self.path.with_extension("lock")
+    }
+
+    /// Load the cache, a missing, corrupted or outdated cache is discarded
+    async fn load(&self) -> Option<CachedResponse> {
+        let contents = tokio::fs::read_to_string(&self.path).await.ok()?;
 
-    now.saturating_sub(cached.cached_at) < CACHE_TTL_SECONDS
+        // the version is checked before deserializing the rest, whose
+        // format depends on it
+        let value: serde_json::Value = serde_json::from_str(&contents).ok()?;
+        if value.get("version").and_then(serde_json::Value::as_u64) != Some(CACHE_VERSION.into()) {
+            return None;
+        }
+
+        let cached: CachedResponse = serde_json::from_value(value).ok()?;
+
+        (cached.source == self.source).then_some(cached)
+    }
+
+    /// Write the cache atomically, concurrent readers see either the
+    /// previous or the new content
+    async fn save(&self, cached_response: &CachedResponse) -> Result<()> {
+        let contents = serde_json::to_string_pretty(cached_response)
+            .context("Failed to serialize cache data")?;
+
+        let temporary = self
+            .path
+            .with_extension(format!("json.{}.tmp", std::process::id()));
+
+        tokio::fs::write(&temporary, contents)
+            .await
+            .context("Failed to write cache file")?;
+
+        tokio::fs::rename(&temporary, &self.path)
+            .await
+            .context("Failed to write cache file")
+    }
+
+    fn is_valid(&self, cached: &CachedResponse) -> bool {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+
+        now.saturating_sub(cached.cached_at) < self.ttl
+    }
 }
 
 fn should_use_cache_for_rate_limiting(cached: &CachedResponse) -> bool {
@@ -304,11 +771,19 @@ fn should_use_cache_for_rate_limiting(cached: &CachedResponse) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::fetch::load_compiler_versions;
-    use octocrab::Octocrab;
-    use wiremock::matchers::{method, path};
+    use super::*;
+    use crate::config::Repository;
+    use semver::Version;
+    use wiremock::matchers::{header, method, path, query_param};
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
+    fn compilers(releases: Releases) -> BTreeMap<Version, MidnightCompiler> {
+        match releases {
+            Releases::Modified { compilers, .. } => compilers,
+            Releases::NotModified => panic!("unexpected `304 Not Modified'"),
+        }
+    }
+
     #[tokio::test]
     async fn test_500_response() {
         let mock_server = MockServer::start().await;
@@ -321,13 +796,13 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let octocrab = Octocrab::builder()
-            .base_uri(mock_server.uri())
-            .unwrap()
-            .build()
-            .unwrap();
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
 
-        let result = load_compiler_versions(&octocrab).await;
+        let result = load_compiler_versions(&github, &Repository::default(), None).await;
         assert!(result.is_err(), "Expected an error due to 500 status code");
         match result {
             Ok(_) => panic!(),
@@ -347,13 +822,13 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let octocrab = Octocrab::builder()
-            .base_uri(mock_server.uri())
-            .unwrap()
-            .build()
-            .unwrap();
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
 
-        let result = load_compiler_versions(&octocrab).await;
+        let result = load_compiler_versions(&github, &Repository::default(), None).await;
         assert!(result.is_err(), "Expected an error due to 400 status code");
         match result {
             Ok(_) => panic!(),
@@ -373,13 +848,13 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let octocrab = Octocrab::builder()
-            .base_uri(mock_server.uri())
-            .unwrap()
-            .build()
-            .unwrap();
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
 
-        let result = load_compiler_versions(&octocrab).await;
+        let result = load_compiler_versions(&github, &Repository::default(), None).await;
         assert!(result.is_err(), "Expected an error due to 404 status code");
         match result {
             Ok(_) => panic!(),
@@ -399,17 +874,377 @@ mod tests {
             .mount(&mock_server)
             .await;
 
-        let octocrab = Octocrab::builder()
-            .base_uri(mock_server.uri())
-            .unwrap()
-            .build()
-            .unwrap();
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
 
-        let result = load_compiler_versions(&octocrab).await;
+        let result = load_compiler_versions(&github, &Repository::default(), None).await;
         assert!(result.is_err(), "Expected an error due to 429 status code");
         match result {
             Ok(_) => panic!(),
             Err(err) => assert_eq!(format!("{err}"), "Error while fetching compact releases"),
         }
     }
+
+    #[tokio::test]
+    async fn test_pagination() {
+        let mock_server = MockServer::start().await;
+
+        let release = |version: u64| {
+            serde_json::json!({
+                "tag_name": format!("compactc-v0.{version}.0"),
+                "assets": [],
+            })
+        };
+
+        let first_page: Vec<_> = (0..100).map(|n| release(100 + n)).collect();
+        let second_page = vec![
+            release(22),
+            serde_json::json!({ "tag_name": "compact-v0.2.0" }),
+        ];
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .and(query_param("per_page", "100"))
+            .and(query_param("page", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(first_page))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .and(query_param("per_page", "100"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(second_page))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
+
+        let (releases, _) = load_compiler_versions(&github, &Repository::default(), None)
+            .await
+            .unwrap();
+        let compilers = compilers(releases);
+
+        assert_eq!(compilers.len(), 101);
+        assert!(compilers.contains_key(&Version::new(0, 22, 0)));
+    }
+
+    #[tokio::test]
+    async fn test_custom_source() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/api/v3/repos/example/compact/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            format!("{}/api/v3", mock_server.uri()).parse().unwrap(),
+            None,
+        );
+
+        let repository = "example/compact".parse().unwrap();
+        let (releases, _) = load_compiler_versions(&github, &repository, None)
+            .await
+            .unwrap();
+        let compilers = compilers(releases);
+
+        assert!(compilers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_release_assets() {
+        let mock_server = MockServer::start().await;
+
+        let asset = |name: &str, digest: Option<&str>| {
+            serde_json::json!({
+                "name": name,
+                "browser_download_url": format!("https://example.com/{name}"),
+                "size": 42,
+                "digest": digest,
+            })
+        };
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "tag_name": "compactc-v0.29.0",
+                    "body": "## What's new\n\n- Faster compilation",
+                    "assets": [
+                        asset("compactc_v0.29.0_x86_64-unknown-linux-musl.zip", Some("sha256:abcd")),
+                        asset("compactc_v0.29.0_x86_64-unknown-linux-musl.zip.sha256", None),
+                        asset("compactc_v0.29.0_x86_64-unknown-linux-musl.zip.minisig", None),
+                        asset("compactc_v0.29.0_aarch64-darwin.zip", None),
+                    ]
+                },
+                {
+                    "tag_name": "runtime-v0.14.0",
+                    "assets": []
+                }
+            ])))
+            .mount(&mock_server)
+            .await;
+
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
+
+        let (releases, _) = load_compiler_versions(&github, &Repository::default(), None)
+            .await
+            .unwrap();
+        let compilers = compilers(releases);
+        assert_eq!(compilers.len(), 1);
+
+        let compiler = &compilers[&Version::new(0, 29, 0)];
+        let x86_linux = compiler.x86_linux.as_ref().unwrap();
+        assert_eq!(
+            x86_linux.name,
+            "compactc_v0.29.0_x86_64-unknown-linux-musl.zip"
+        );
+        assert_eq!(x86_linux.digest.as_deref(), Some("sha256:abcd"));
+        assert_eq!(
+            compiler.aarch64_macos.as_ref().unwrap().digest.as_deref(),
+            None
+        );
+        assert_eq!(compiler.checksums.len(), 1);
+        assert_eq!(
+            compiler.checksums[0].name,
+            "compactc_v0.29.0_x86_64-unknown-linux-musl.zip.sha256"
+        );
+        assert_eq!(compiler.signatures.len(), 1);
+        assert_eq!(
+            compiler.signatures[0].name,
+            "compactc_v0.29.0_x86_64-unknown-linux-musl.zip.minisig"
+        );
+        assert_eq!(
+            compiler.notes.as_deref(),
+            Some("## What's new\n\n- Faster compilation")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_conditional_request() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .and(header("If-None-Match", "\"abc\""))
+            .respond_with(
+                ResponseTemplate::new(304)
+                    .insert_header("X-RateLimit-Remaining", "42")
+                    .insert_header("X-RateLimit-Reset", "1700000000"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("ETag", "\"abc\"")
+                    .insert_header("X-RateLimit-Remaining", "43")
+                    .insert_header("X-RateLimit-Reset", "1700000000")
+                    .set_body_json(serde_json::json!([])),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
+
+        let (releases, rate_limit) = load_compiler_versions(&github, &Repository::default(), None)
+            .await
+            .unwrap();
+        assert!(
+            matches!(releases, Releases::Modified { etag: Some(etag), .. } if etag == "\"abc\"")
+        );
+        assert_eq!(
+            rate_limit,
+            RateLimit {
+                remaining: Some(43),
+                reset: Some(1700000000)
+            }
+        );
+
+        let (releases, rate_limit) =
+            load_compiler_versions(&github, &Repository::default(), Some("\"abc\""))
+                .await
+                .unwrap();
+        assert!(matches!(releases, Releases::NotModified));
+        assert_eq!(rate_limit.remaining, Some(42));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_exceeded() {
+        let mock_server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/repos/midnightntwrk/compact/releases"))
+            .respond_with(
+                ResponseTemplate::new(403)
+                    .insert_header("X-RateLimit-Remaining", "0")
+                    .insert_header("X-RateLimit-Reset", "1700000000")
+                    .set_body_json(serde_json::json!({
+                        "message": "API rate limit exceeded"
+                    })),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let github = GitHub::new(
+            http::Client::new().unwrap(),
+            mock_server.uri().parse().unwrap(),
+            None,
+        );
+
+        // not retried
+        let error = load_compiler_versions_with_retry(&github, &Repository::default(), None)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.downcast_ref::<RateLimitExceeded>().unwrap().reset,
+            Some(1700000000)
+        );
+    }
+
+    #[test]
+    fn test_cache_for_rate_limiting() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        let cached = |remaining, reset| CachedResponse {
+            artifacts: MidnightArtifacts::default(),
+            cached_at: 0,
+            rate_limit_remaining: Some(remaining),
+            rate_limit_reset: Some(reset),
+            etag: None,
+            version: CACHE_VERSION,
+            source: String::new(),
+        };
+
+        assert!(should_use_cache_for_rate_limiting(&cached(5, now + 600)));
+        assert!(!should_use_cache_for_rate_limiting(&cached(5, now - 1)));
+        assert!(!should_use_cache_for_rate_limiting(&cached(50, now + 600)));
+    }
+
+    fn cached_response(cache: &Cache, cached_at: u64) -> CachedResponse {
+        CachedResponse {
+            version: CACHE_VERSION,
+            source: cache.source.clone(),
+            artifacts: MidnightArtifacts::default(),
+            cached_at,
+            rate_limit_remaining: None,
+            rate_limit_reset: None,
+            etag: Some("\"abc\"".to_owned()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::in_dir(dir.path(), &ReleaseSource::default(), 900).unwrap();
+
+        assert_eq!(cache.load().await, None);
+
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let cached = cached_response(&cache, now);
+        cache.save(&cached).await.unwrap();
+
+        let loaded = cache.load().await.unwrap();
+        assert_eq!(loaded, cached);
+        assert!(cache.is_valid(&loaded));
+        assert!(!cache.is_valid(&cached_response(&cache, now - 900)));
+
+        // only the cache file is left
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_per_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::in_dir(dir.path(), &ReleaseSource::default(), 900).unwrap();
+
+        let other_source = ReleaseSource {
+            repository: "example/compact".parse().unwrap(),
+            ..ReleaseSource::default()
+        };
+        let other = Cache::in_dir(dir.path(), &other_source, 900).unwrap();
+
+        assert_ne!(cache.path, other.path);
+
+        cache.save(&cached_response(&cache, 0)).await.unwrap();
+        assert_eq!(other.load().await, None);
+    }
+
+    #[tokio::test]
+    async fn test_cache_discarded() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::in_dir(dir.path(), &ReleaseSource::default(), 900).unwrap();
+
+        // an older format, without version
+        std::fs::write(
+            &cache.path,
+            r#"{ "artifacts": { "compilers": {} }, "cached_at": 0 }"#,
+        )
+        .unwrap();
+        assert_eq!(cache.load().await, None);
+
+        // a newer format
+        let mut value = serde_json::to_value(cached_response(&cache, 0)).unwrap();
+        value["version"] = (CACHE_VERSION + 1).into();
+        std::fs::write(&cache.path, value.to_string()).unwrap();
+        assert_eq!(cache.load().await, None);
+
+        std::fs::write(&cache.path, "{ corrupted").unwrap();
+        assert_eq!(cache.load().await, None);
+
+        // replaced on the next save
+        cache.save(&cached_response(&cache, 0)).await.unwrap();
+        assert!(cache.load().await.is_some());
+    }
+
+    #[test]
+    fn test_corrupt_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::in_dir(dir.path(), &ReleaseSource::default(), 900).unwrap();
+        let cached = serde_json::to_value(cached_response(&cache, 0)).unwrap();
+
+        assert!(is_readable_cache(&cached.to_string()));
+        // written by other versions of compact
+        assert!(is_readable_cache(r#"{ "artifacts": {}, "cached_at": 0 }"#));
+        assert!(is_readable_cache(r#"{ "version": 99 }"#));
+
+        assert!(!is_readable_cache("{ corrupted"));
+        assert!(!is_readable_cache("[]"));
+        assert!(!is_readable_cache(&format!(
+            r#"{{ "version": {CACHE_VERSION}, "cached_at": "now" }}"#
+        )));
+    }
 }
@@ -17,7 +17,7 @@ use anyhow::{Context as _, Result};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 
-use crate::formatter::diff_file;
+use crate::{formatter::diff_file, utils};
 
 #[derive(Debug)]
 pub enum FixupStatus {
@@ -54,10 +54,9 @@ pub async fn fixup_file(
     };
     command.args(args);
 
-    let output = command
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .output()
+    command.stdout(Stdio::piped()).stderr(Stdio::piped());
+
+    let output = utils::command_output(&mut command)
         .await
         .context("Failed to spawn fixup-compact command")?;
 
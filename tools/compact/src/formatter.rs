@@ -22,6 +22,8 @@ use anyhow::{Context as _, Result};
 use console::{Color, style};
 use similar::{ChangeTag, TextDiff};
 
+use crate::utils;
+
 #[derive(Debug)]
 pub enum FormatStatus {
     Error,
@@ -46,10 +48,9 @@ pub async fn format_file(
     };
 
     let mut cmd = tokio::process::Command::new(bin);
-    let output = cmd
-        .args(args)
-        .stdout(Stdio::piped())
-        .output()
+    cmd.args(args).stdout(Stdio::piped());
+
+    let output = utils::command_output(&mut cmd)
         .await
         .context("Failed to spawn format-compact command")?;
 
@@ -14,54 +14,103 @@
 // limitations under the License.
 
 use crate::{
+    CommandLineArguments,
+    config::NetworkSettings,
     file::{File, Writer},
     progress::{HasLength, HasProgress, Poll},
 };
-use anyhow::{Context, Result, anyhow};
+use anyhow::{Context, Result, anyhow, ensure};
 use bytes::{Buf, Bytes};
 use futures::{Stream, StreamExt as _};
-use reqwest::{IntoUrl, header};
+use reqwest::{IntoUrl, StatusCode, Url, header};
 use serde::de::DeserializeOwned;
-use std::pin::Pin;
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+    pin::Pin,
+    time::{Duration, UNIX_EPOCH},
+};
+use tokio::{
+    io::{AsyncReadExt as _, AsyncSeekExt as _},
+    time::Instant,
+};
 
+const FILE_CHUNK_SIZE: usize = 64 * 1024;
 const APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
+/// Attempts to resume a download failing mid-stream
+const MAX_DOWNLOAD_RETRIES: u32 = 3;
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Suffix of the file next to a partial download recording the validator
+/// (`ETag` or `Last-Modified`) of the content being downloaded
+const VALIDATOR_SUFFIX: &str = ".etag";
 
 /// handle HTTP client requests
+#[derive(Clone)]
 pub struct Client {
     client: reqwest::Client,
+    retry_delay: Duration,
 }
 
 pub struct Download {
     content_length: Option<u64>,
     downloaded: u64,
+    validator: Option<String>,
     content: Pin<Box<dyn Stream<Item = Result<Bytes>>>>,
 }
 
+/// Download to a `.partial` file, renamed once complete
+///
+/// An interrupted download is resumed from the partial file when the server
+/// still serves the same content, checked with `If-Range` against the
+/// validator recorded when the download started. A download failing
+/// mid-stream is resumed the same way, with a backoff between the attempts.
 pub struct DownloadToFile {
+    client: Client,
+    url: String,
     download: Download,
     file: File,
     partial_file: File,
     partial_writer: Writer,
     written: u64,
+    retries: u32,
 }
 
 impl DownloadToFile {
     async fn new(client: &Client, url: impl IntoUrl, file: File) -> Result<Self> {
+        let url = url.as_str().to_owned();
         let partial_file = file.to_partial_file()?;
 
-        let partial_size = partial_file.size().await.ok();
+        // a partial download without validator can't be safely resumed
+        let validator = tokio::fs::read_to_string(validator_path(&partial_file))
+            .await
+            .ok();
+        let partial_size = partial_file
+            .size()
+            .await
+            .ok()
+            .filter(|size| *size > 0 && validator.is_some());
 
-        let written = partial_size.unwrap_or_default();
-        let download = client.download(url, partial_size).await?;
+        let download = client
+            .download_(&url, partial_size, validator.as_deref())
+            .await
+            .with_context(|| anyhow!("Error with Download request to: `{url}'"))?;
+
+        let resumed = download.downloaded() > 0;
+        let partial_writer = partial_file.writer(true, !resumed).await?;
 
-        let partial_writer = partial_file.writer(true, false).await?;
+        if !resumed {
+            save_validator(&partial_file, download.validator()).await?;
+        }
 
         Ok(Self {
+            client: client.clone(),
+            url,
+            written: download.downloaded(),
             download,
             file,
             partial_file,
             partial_writer,
-            written,
+            retries: 0,
         })
     }
 
@@ -74,15 +123,100 @@ impl DownloadToFile {
     }
 
     pub async fn step(&mut self) -> Result<Option<()>> {
-        if let Some(mut chunk) = self.download.next().await? {
-            while chunk.has_remaining() {
-                self.written += self.partial_writer.write(&mut chunk).await?;
-            }
-            Ok(Some(()))
+        Ok(if self.advance().await? {
+            None
         } else {
-            self.partial_file.rename(&self.file).await?;
+            Some(())
+        })
+    }
+
+    /// Write the next chunk to the partial file, returns true once the
+    /// download is complete and the file renamed
+    async fn advance(&mut self) -> Result<bool> {
+        loop {
+            match self.download.next().await {
+                Ok(Some(mut chunk)) => {
+                    self.retries = 0;
+
+                    while chunk.has_remaining() {
+                        self.written += self.partial_writer.write(&mut chunk).await?;
+                    }
+
+                    return Ok(false);
+                }
+                Ok(None) => {
+                    let _ = tokio::fs::remove_file(validator_path(&self.partial_file)).await;
+                    self.partial_file.rename(&self.file).await?;
+
+                    return Ok(true);
+                }
+                Err(error) => self.retry(error).await?,
+            }
+        }
+    }
+
+    /// Resume the download after `error`, waiting longer after each attempt
+    async fn retry(&mut self, mut error: anyhow::Error) -> Result<()> {
+        while self.retries < MAX_DOWNLOAD_RETRIES {
+            self.retries += 1;
+            tracing::debug!(url = %self.url, attempt = self.retries, error = %format!("{error:#}"), "retrying download");
+            tokio::time::sleep(self.client.retry_delay * 2u32.pow(self.retries - 1)).await;
+
+            // a failure to reconnect counts as another attempt
+            match self.resume().await {
+                Ok(()) => return Ok(()),
+                Err(resume_error) => error = resume_error,
+            }
+        }
+
+        Err(error).with_context(|| {
+            anyhow!(
+                "Download of `{}' failed after {} attempts",
+                self.url,
+                self.retries + 1
+            )
+        })
+    }
+
+    /// Request the rest of the content, or all of it again when the server
+    /// can't resume the download
+    async fn resume(&mut self) -> Result<()> {
+        let validator = self.download.validator().map(str::to_owned);
+        let from = Some(self.written).filter(|written| *written > 0 && validator.is_some());
+
+        let download = self
+            .client
+            .download_(&self.url, from, validator.as_deref())
+            .await?;
+
+        if download.downloaded() == 0 {
+            self.partial_writer = self.partial_file.writer(true, true).await?;
+            self.written = 0;
+            save_validator(&self.partial_file, download.validator()).await?;
+        }
+
+        self.download = download;
 
-            Ok(None)
+        Ok(())
+    }
+}
+
+fn validator_path(partial_file: &File) -> PathBuf {
+    let mut path = partial_file.as_ref().as_os_str().to_os_string();
+    path.push(VALIDATOR_SUFFIX);
+    PathBuf::from(path)
+}
+
+async fn save_validator(partial_file: &File, validator: Option<&str>) -> Result<()> {
+    let path = validator_path(partial_file);
+
+    match validator {
+        Some(validator) => tokio::fs::write(&path, validator)
+            .await
+            .with_context(|| anyhow!("Failed to write `{}'", path.display())),
+        None => {
+            let _ = tokio::fs::remove_file(&path).await;
+            Ok(())
         }
     }
 }
@@ -110,15 +244,10 @@ impl HasProgress for DownloadToFile {
     }
 
     async fn tick(&mut self) -> Result<Poll<Self::Output>> {
-        if let Some(mut chunk) = self.download.next().await? {
-            while chunk.has_remaining() {
-                self.written += self.partial_writer.write(&mut chunk).await?;
-            }
-            Ok(Poll::Continue)
-        } else {
-            self.partial_file.rename(&self.file).await?;
-
+        if self.advance().await? {
             Ok(Poll::Done(self.file.clone()))
+        } else {
+            Ok(Poll::Continue)
         }
     }
 }
@@ -132,10 +261,15 @@ impl Download {
         Self {
             content_length,
             downloaded,
+            validator: None,
             content: Box::pin(content),
         }
     }
 
+    fn with_validator(self, validator: Option<String>) -> Self {
+        Self { validator, ..self }
+    }
+
     /// return the number of bytes already processed so far
     #[inline]
     pub fn downloaded(&self) -> u64 {
@@ -151,26 +285,102 @@ impl Download {
         self.content_length
     }
 
+    /// Identifies the content downloaded, to resume the download with
+    /// `If-Range`
+    #[inline]
+    pub fn validator(&self) -> Option<&str> {
+        self.validator.as_deref()
+    }
+
     pub async fn next(&mut self) -> Result<Option<Bytes>> {
         let Some(result) = self.content.next().await else {
+            if let Some(content_length) = self.content_length {
+                ensure!(
+                    self.downloaded == content_length,
+                    "Download incomplete, received {} of {content_length} bytes",
+                    self.downloaded
+                );
+            }
+
             return Ok(None);
         };
 
         let bytes = result.context("Failed to download anymore bytes")?;
 
         self.downloaded += bytes.len() as u64;
+
+        if let Some(content_length) = self.content_length {
+            ensure!(
+                self.downloaded <= content_length,
+                "Received more than the {content_length} bytes expected"
+            );
+        }
+
         Ok(Some(bytes))
     }
 }
 
 impl Client {
+    /// A client with the default network settings
     pub fn new() -> Result<Self> {
-        let client = reqwest::ClientBuilder::new()
+        Self::with_settings(&NetworkSettings::default())
+    }
+
+    /// A client with the network settings of the command line, the
+    /// environment and the configuration file
+    pub async fn load(cfg: &CommandLineArguments) -> Result<Self> {
+        let settings = NetworkSettings::load(cfg).await?;
+
+        Self::with_settings(&settings)
+    }
+
+    pub fn with_settings(settings: &NetworkSettings) -> Result<Self> {
+        let mut builder = reqwest::ClientBuilder::new()
             .gzip(true)
             .user_agent(APP_USER_AGENT)
-            .build()
-            .context("Failed to create HTTP Client")?;
-        Ok(Self { client })
+            .connect_timeout(settings.connect_timeout)
+            .read_timeout(settings.read_timeout);
+
+        // without it, the proxy of the environment is used
+        if let Some(proxy) = &settings.proxy {
+            let proxy = reqwest::Proxy::all(proxy.clone())
+                .with_context(|| anyhow!("Invalid proxy `{proxy}'"))?
+                .no_proxy(reqwest::NoProxy::from_env());
+
+            builder = builder.proxy(proxy);
+        }
+
+        if let Some(path) = &settings.ca_bundle {
+            for certificate in read_ca_bundle(path)? {
+                builder = builder.add_root_certificate(certificate);
+            }
+        }
+
+        let client = builder.build().context("Failed to create HTTP Client")?;
+
+        Ok(Self {
+            client,
+            retry_delay: RETRY_BASE_DELAY,
+        })
+    }
+
+    /// Send `request`, tracing its status and latency
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<reqwest::Response> {
+        let request = request.build().context("Failed to create HTTP request")?;
+        let (method, url) = (request.method().clone(), request.url().clone());
+        let start = Instant::now();
+
+        let response = self.client.execute(request).await;
+        let elapsed = start.elapsed();
+
+        match &response {
+            Ok(response) => {
+                tracing::debug!(%method, %url, status = %response.status(), ?elapsed, "HTTP request")
+            }
+            Err(error) => tracing::debug!(%method, %url, %error, ?elapsed, "HTTP request failed"),
+        }
+
+        response.context("Failed to execute HTTP Get request")
     }
 
     pub async fn download_to_file<U>(&self, url: U, file: File) -> Result<DownloadToFile>
@@ -180,48 +390,128 @@ impl Client {
         DownloadToFile::new(self, url, file).await
     }
 
+    /// Download to `file` from the first of `urls` that answers, the
+    /// following ones being fallback mirrors
+    pub async fn download_to_file_from(&self, urls: &[Url], file: File) -> Result<DownloadToFile> {
+        first_success(urls, |url| self.download_to_file(url.clone(), file.clone())).await
+    }
+
     pub async fn download<U>(&self, url: U, from: Option<u64>) -> Result<Download>
     where
         U: IntoUrl,
     {
         let raw_url = url.as_str().to_owned();
 
-        self.download_(url, from)
+        self.download_(&raw_url, from, None)
             .await
             .with_context(|| anyhow!("Error with Download request to: `{raw_url}'"))
     }
 
-    async fn download_<U>(&self, url: U, from: Option<u64>) -> Result<Download>
-    where
-        U: IntoUrl,
-    {
-        let raw_url = url.as_str().to_owned();
-        let url = url
-            .into_url()
-            .with_context(|| anyhow!("Not a valid URL: {raw_url}"))?;
+    /// Request the content from the byte `from`, when it still matches the
+    /// validator `if_range`. The content is served from the start otherwise,
+    /// which the `downloaded` count of the result tells.
+    async fn download_(
+        &self,
+        url: &str,
+        from: Option<u64>,
+        if_range: Option<&str>,
+    ) -> Result<Download> {
+        // reqwest doesn't serve local files
+        if let Some(url) = file_url(url) {
+            return read_file(&url, from, if_range).await;
+        }
+
+        let url = Url::parse(url).with_context(|| anyhow!("Not a valid URL: {url}"))?;
+
         let mut request = self.client.get(url.clone());
-        let downloaded = if let Some(from) = from {
+        if let Some(from) = from {
             request = request.header(header::RANGE, format!("bytes={from}-"));
-            from
-        } else {
-            0
-        };
-        let request = request.build().context("Failed to create HTTP request")?;
 
-        let response = self
-            .client
-            .execute(request)
-            .await
-            .context("Failed to execute HTTP Get request")?
+            if let Some(if_range) = if_range {
+                request = request.header(header::IF_RANGE, if_range);
+            }
+        }
+
+        let response = self.send(request).await?;
+
+        // the partial content is larger than the content, which changed
+        if response.status() == StatusCode::RANGE_NOT_SATISFIABLE && from.is_some() {
+            return Box::pin(self.download_(url.as_str(), None, None)).await;
+        }
+
+        let response = response
             .error_for_status()
             .context("HTTP Get request returned error")?;
 
+        // a server ignoring the range, or whose content changed, replies
+        // with the whole content
+        let downloaded = match from {
+            Some(from) if response.status() == StatusCode::PARTIAL_CONTENT => {
+                let start = content_range_start(response.headers())?;
+                ensure!(
+                    start == from,
+                    "Server resumed the download at byte {start} instead of {from}"
+                );
+                from
+            }
+            _ => 0,
+        };
+
+        let validator = response_validator(response.headers());
         let content_length = response.content_length().map(|c| downloaded + c);
         let content_download = response
             .bytes_stream()
             .map(move |v| v.with_context(|| anyhow!("Error while receiving bytes from `{url}'")));
 
-        Ok(Download::new(content_length, content_download, downloaded))
+        Ok(Download::new(content_length, content_download, downloaded).with_validator(validator))
+    }
+
+    pub async fn get_text<U>(&self, url: U) -> Result<String>
+    where
+        U: IntoUrl,
+    {
+        let raw_url = url.as_str().to_owned();
+
+        self.get_text_(url)
+            .await
+            .with_context(|| anyhow!("Error with HTTP GET request to: `{raw_url}'"))
+    }
+
+    #[inline]
+    async fn get_text_<U>(&self, url: U) -> Result<String>
+    where
+        U: IntoUrl,
+    {
+        if let Some(url) = file_url(url.as_str()) {
+            let path = local_path(&url)?;
+
+            return tokio::fs::read_to_string(&path)
+                .await
+                .with_context(|| anyhow!("Failed to read `{path:?}'"));
+        }
+
+        let response = self
+            .send(self.client.get(url))
+            .await?
+            .error_for_status()
+            .context("HTTP Get request returned error")?;
+
+        response
+            .text()
+            .await
+            .context("Failed to decode the response body")
+    }
+
+    /// Get the text content of the first of `urls` that answers, the
+    /// following ones being fallback mirrors
+    pub async fn get_text_from(&self, urls: &[Url]) -> Result<String> {
+        first_success(urls, |url| self.get_text(url.clone())).await
+    }
+
+    /// Send a GET request with `headers`, whatever the status of the
+    /// response
+    pub async fn get(&self, url: Url, headers: header::HeaderMap) -> Result<reqwest::Response> {
+        self.send(self.client.get(url).headers(headers)).await
     }
 
     pub async fn get_json<O, U>(&self, url: U) -> Result<O>
@@ -242,18 +532,11 @@ impl Client {
         U: IntoUrl,
         O: DeserializeOwned,
     {
-        let request = self
-            .client
-            .get(url)
-            .header("Accept", "application/json")
-            .build()
-            .context("Failed to create HTTP request")?;
+        let request = self.client.get(url).header("Accept", "application/json");
 
         let response = self
-            .client
-            .execute(request)
-            .await
-            .context("Failed to execute HTTP Get request")?
+            .send(request)
+            .await?
             .error_for_status()
             .context("HTTP Get request returned error")?;
 
@@ -263,3 +546,408 @@ impl Client {
             .context("Failed to decode the JSON response body")
     }
 }
+
+fn read_ca_bundle(path: &Path) -> Result<Vec<reqwest::Certificate>> {
+    let pem = std::fs::read(path)
+        .with_context(|| anyhow!("Failed to read CA bundle `{}'", path.display()))?;
+
+    let certificates = reqwest::Certificate::from_pem_bundle(&pem)
+        .with_context(|| anyhow!("Invalid CA bundle `{}'", path.display()))?;
+
+    ensure!(
+        !certificates.is_empty(),
+        "No certificate found in CA bundle `{}'",
+        path.display()
+    );
+
+    Ok(certificates)
+}
+
+fn file_url(url: &str) -> Option<Url> {
+    Url::parse(url).ok().filter(|url| url.scheme() == "file")
+}
+
+/// Start of the range of a `206 Partial Content` response
+fn content_range_start(headers: &header::HeaderMap) -> Result<u64> {
+    let range = headers
+        .get(header::CONTENT_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .context("Partial content without a Content-Range")?;
+
+    // bytes <start>-<end>/<length>
+    range
+        .strip_prefix("bytes ")
+        .and_then(|range| range.split_once('-'))
+        .and_then(|(start, _)| start.parse().ok())
+        .with_context(|| anyhow!("Invalid Content-Range `{range}'"))
+}
+
+/// The strong `ETag` of a response, or else its `Last-Modified` date, the
+/// only validators allowed in `If-Range`
+fn response_validator(headers: &header::HeaderMap) -> Option<String> {
+    let header = |name| {
+        headers
+            .get(name)
+            .and_then(|value: &header::HeaderValue| value.to_str().ok())
+    };
+
+    header(header::ETAG)
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| header(header::LAST_MODIFIED))
+        .map(str::to_owned)
+}
+
+fn local_path(url: &Url) -> Result<PathBuf> {
+    url.to_file_path()
+        .map_err(|()| anyhow!("Not a local path: {url}"))
+}
+
+/// Stream a local file, for the releases served by a local registry
+///
+/// The size and the modification time of the file are its validator.
+async fn read_file(url: &Url, from: Option<u64>, if_range: Option<&str>) -> Result<Download> {
+    let path = local_path(url)?;
+    tracing::debug!(path = %path.display(), ?from, "reading local file");
+
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .with_context(|| anyhow!("Failed to open `{path:?}'"))?;
+    let metadata = file.metadata().await?;
+    let length = metadata.len();
+    let validator = file_validator(&path, &metadata)?;
+
+    let downloaded = from
+        .filter(|from| *from <= length && if_range.is_none_or(|v| v == validator))
+        .unwrap_or_default();
+    file.seek(SeekFrom::Start(downloaded)).await?;
+
+    let content = futures::stream::try_unfold(file, move |mut file| {
+        let path = path.clone();
+
+        async move {
+            let mut buffer = vec![0; FILE_CHUNK_SIZE];
+            let read = file
+                .read(&mut buffer)
+                .await
+                .with_context(|| anyhow!("Error while reading `{path:?}'"))?;
+
+            if read == 0 {
+                return Ok(None);
+            }
+
+            buffer.truncate(read);
+            Ok(Some((Bytes::from(buffer), file)))
+        }
+    });
+
+    Ok(Download::new(Some(length), content, downloaded).with_validator(Some(validator)))
+}
+
+fn file_validator(path: &Path, metadata: &std::fs::Metadata) -> Result<String> {
+    let modified = metadata
+        .modified()
+        .with_context(|| anyhow!("Failed to read metadata of `{path:?}'"))?
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+
+    Ok(format!("{}-{}", metadata.len(), modified.as_nanos()))
+}
+
+async fn first_success<T, F, Fut>(urls: &[Url], mut request: F) -> Result<T>
+where
+    F: FnMut(&Url) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut last_error = None;
+
+    for url in urls {
+        match request(url).await {
+            Ok(value) => return Ok(value),
+            Err(error) => last_error = Some(error),
+        }
+    }
+
+    match last_error {
+        Some(error) if urls.len() > 1 => {
+            Err(error).with_context(|| anyhow!("All {} download sources failed", urls.len()))
+        }
+        Some(error) => Err(error),
+        None => Err(anyhow!("No URL to download from")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wiremock::matchers::{header, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    #[tokio::test]
+    async fn fallback_to_mirror() {
+        let origin = MockServer::start().await;
+        let mirror = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .respond_with(ResponseTemplate::new(404))
+            .expect(2)
+            .mount(&origin)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("content"))
+            .mount(&mirror)
+            .await;
+
+        let urls = [
+            format!("{}/asset.txt", origin.uri()).parse().unwrap(),
+            format!("{}/asset.txt", mirror.uri()).parse().unwrap(),
+        ];
+
+        let client = Client::new().unwrap();
+        assert_eq!(client.get_text_from(&urls).await.unwrap(), "content");
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::new(dir.path().join("asset.txt"));
+        let download = client.download_to_file_from(&urls, file).await.unwrap();
+        crate::progress::progress(download).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("asset.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[tokio::test]
+    async fn local_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("asset.txt"), "content").unwrap();
+
+        let url = Url::from_file_path(dir.path().join("asset.txt")).unwrap();
+        let client = Client::new().unwrap();
+
+        assert_eq!(client.get_text(url.clone()).await.unwrap(), "content");
+
+        let mut download = client.download(url, Some(3)).await.unwrap();
+        assert_eq!(download.content_length(), Some(7));
+        assert_eq!(download.next().await.unwrap().unwrap(), "tent");
+        assert!(download.next().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn all_sources_fail() {
+        let origin = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(500))
+            .mount(&origin)
+            .await;
+
+        let urls = [
+            format!("{}/a.txt", origin.uri()).parse().unwrap(),
+            format!("{}/b.txt", origin.uri()).parse().unwrap(),
+        ];
+
+        let error = Client::new()
+            .unwrap()
+            .get_text_from(&urls)
+            .await
+            .unwrap_err();
+        assert_eq!(error.to_string(), "All 2 download sources failed");
+    }
+
+    fn client() -> Client {
+        Client {
+            retry_delay: Duration::from_millis(1),
+            ..Client::new().unwrap()
+        }
+    }
+
+    #[tokio::test]
+    async fn resume_partial_download() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .and(header("range", "bytes=3-"))
+            .and(header("if-range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 3-6/7")
+                    .insert_header("etag", "\"v1\"")
+                    .set_body_string("tent"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("asset.txt.partial"), "con").unwrap();
+        std::fs::write(dir.path().join("asset.txt.partial.etag"), "\"v1\"").unwrap();
+
+        let file = File::new(dir.path().join("asset.txt"));
+        let download = client()
+            .download_to_file(format!("{}/asset.txt", server.uri()), file)
+            .await
+            .unwrap();
+        assert_eq!(download.downloaded(), 3);
+        crate::progress::progress(download).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("asset.txt")).unwrap(),
+            "content"
+        );
+        assert!(!dir.path().join("asset.txt.partial.etag").exists());
+    }
+
+    #[tokio::test]
+    async fn restart_when_range_is_ignored() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("etag", "\"v2\"")
+                    .set_body_string("content"),
+            )
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("asset.txt.partial"), "old").unwrap();
+        std::fs::write(dir.path().join("asset.txt.partial.etag"), "\"v1\"").unwrap();
+
+        let file = File::new(dir.path().join("asset.txt"));
+        let download = client()
+            .download_to_file(format!("{}/asset.txt", server.uri()), file)
+            .await
+            .unwrap();
+        assert_eq!(download.downloaded(), 0);
+        crate::progress::progress(download).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("asset.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[tokio::test]
+    async fn retry_failed_stream() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .and(header("range", "bytes=3-"))
+            .and(header("if-range", "\"v1\""))
+            .respond_with(
+                ResponseTemplate::new(206)
+                    .insert_header("content-range", "bytes 3-6/7")
+                    .set_body_string("tent"),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let dir = tempfile::tempdir().unwrap();
+        let file = File::new(dir.path().join("asset.txt"));
+        let partial_file = file.to_partial_file().unwrap();
+
+        // the connection is lost after the first chunk
+        let content =
+            futures::stream::iter([Ok(Bytes::from("con")), Err(anyhow!("connection reset"))]);
+
+        let mut download = DownloadToFile {
+            client: client(),
+            url: format!("{}/asset.txt", server.uri()),
+            download: Download::new(Some(7), content, 0).with_validator(Some("\"v1\"".into())),
+            file,
+            partial_writer: partial_file.writer(true, true).await.unwrap(),
+            partial_file,
+            written: 0,
+            retries: 0,
+        };
+
+        while download.step().await.unwrap().is_some() {}
+
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("asset.txt")).unwrap(),
+            "content"
+        );
+    }
+
+    #[tokio::test]
+    async fn incomplete_download() {
+        let content = futures::stream::iter([Ok(Bytes::from("con"))]);
+        let mut download = Download::new(Some(7), content, 0);
+
+        assert_eq!(download.next().await.unwrap().unwrap(), "con");
+        assert_eq!(
+            download.next().await.unwrap_err().to_string(),
+            "Download incomplete, received 3 of 7 bytes"
+        );
+    }
+
+    #[tokio::test]
+    async fn requests_go_through_the_proxy() {
+        let proxy = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/asset.txt"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("proxied"))
+            .expect(1)
+            .mount(&proxy)
+            .await;
+
+        let client = Client::with_settings(&NetworkSettings {
+            proxy: Some(proxy.uri().parse().unwrap()),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert_eq!(
+            client
+                .get_text("http://releases.example.com/asset.txt")
+                .await
+                .unwrap(),
+            "proxied"
+        );
+    }
+
+    #[tokio::test]
+    async fn read_timeout() {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(5)))
+            .mount(&server)
+            .await;
+
+        let client = Client::with_settings(&NetworkSettings {
+            read_timeout: Duration::from_millis(100),
+            ..Default::default()
+        })
+        .unwrap();
+
+        assert!(client.get_text(server.uri()).await.is_err());
+    }
+
+    #[test]
+    fn invalid_ca_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ca.pem");
+        std::fs::write(&path, "not a certificate").unwrap();
+
+        let error = Client::with_settings(&NetworkSettings {
+            ca_bundle: Some(path.clone()),
+            ..Default::default()
+        })
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            error.to_string(),
+            format!("No certificate found in CA bundle `{}'", path.display())
+        );
+    }
+}
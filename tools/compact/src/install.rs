@@ -0,0 +1,387 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Crash-safe installation of toolchains
+//!
+//! A toolchain is downloaded and extracted in a staging directory,
+//! `<compact directory>/tmp/<version>-<target>`, validated, and only then
+//! renamed into `versions/<version>/<target>`. An interrupted installation
+//! never leaves a partial toolchain in the versions directory, and its
+//! staging directory is cleaned up by the next installation.
+
+use crate::{CommandLineArguments, Target};
+use anyhow::{Context, Result, anyhow, bail};
+use semver::Version;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+/// Files of the download kept across interrupted installations, so it can be
+/// resumed (it is verified again before extraction)
+pub const DOWNLOAD_FILES: [&str; 3] = [
+    "artifact.zip",
+    "artifact.zip.partial",
+    "artifact.zip.partial.etag",
+];
+
+/// Versions older than this one ship `compactc` only
+const FIRST_VERSION_WITH_TOOLS: Version = Version::new(0, 25, 0);
+
+/// Returns true if the toolchain is fully installed
+pub fn is_installed(cfg: &CommandLineArguments, version: &Version, target: Target) -> bool {
+    install_dir(cfg, version, target).join("compactc").is_file()
+}
+
+fn install_dir(cfg: &CommandLineArguments, version: &Version, target: Target) -> PathBuf {
+    cfg.directory
+        .versions_dir()
+        .join(version.to_string())
+        .join(target.to_string())
+}
+
+/// Path of the staging directory of a toolchain
+pub fn staging_dir(cfg: &CommandLineArguments, version: &Version, target: Target) -> PathBuf {
+    cfg.directory
+        .staging_dir()
+        .join(format!("{version}-{target}"))
+}
+
+/// Prepare the staging directory of a toolchain
+///
+/// The staging directories left by interrupted installations are removed,
+/// except for the download of this toolchain so it can be resumed.
+pub async fn prepare_staging(
+    cfg: &CommandLineArguments,
+    version: &Version,
+    target: Target,
+) -> Result<PathBuf> {
+    let staging = staging_dir(cfg, version, target);
+
+    clean_staging(cfg, Some(&staging)).await?;
+
+    match fs::read_dir(&staging).await {
+        Ok(mut entries) => {
+            while let Some(entry) = entries
+                .next_entry()
+                .await
+                .with_context(|| anyhow!("Failed to read directory `{}'", staging.display()))?
+            {
+                if !DOWNLOAD_FILES.iter().any(|name| entry.file_name() == *name) {
+                    remove_path(&entry.path()).await?;
+                }
+            }
+        }
+        Err(error) if error.kind() == ErrorKind::NotFound => {
+            fs::create_dir_all(&staging)
+                .await
+                .with_context(|| anyhow!("Failed to create directory `{}'", staging.display()))?;
+        }
+        Err(error) => {
+            return Err(error)
+                .with_context(|| anyhow!("Failed to read directory `{}'", staging.display()));
+        }
+    }
+
+    Ok(staging)
+}
+
+/// Remove the staging directories, but `keep`
+pub async fn clean_staging(cfg: &CommandLineArguments, keep: Option<&Path>) -> Result<()> {
+    let dir = cfg.directory.staging_dir();
+
+    let mut entries = match fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(error) => {
+            return Err(error)
+                .with_context(|| anyhow!("Failed to read directory `{}'", dir.display()));
+        }
+    };
+
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .with_context(|| anyhow!("Failed to read directory `{}'", dir.display()))?
+    {
+        let path = entry.path();
+
+        if Some(path.as_path()) != keep {
+            remove_path(&path).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Check the staged toolchain contains its programs, and that they are
+/// executable
+pub fn validate(staging: &Path, version: &Version) -> Result<()> {
+    if let Some(problem) = program_problems(staging, version)?.first() {
+        bail!("Invalid toolchain archive: {problem}");
+    }
+
+    Ok(())
+}
+
+/// The programs of the toolchain in `dir` that are missing or not
+/// executable
+pub fn program_problems(dir: &Path, version: &Version) -> Result<Vec<String>> {
+    let programs: &[&str] = if *version >= FIRST_VERSION_WITH_TOOLS {
+        &["compactc", "format-compact", "fixup-compact"]
+    } else {
+        &["compactc"]
+    };
+
+    let mut problems = Vec::new();
+
+    for program in programs {
+        let path = dir.join(program);
+
+        if !path.is_file() {
+            problems.push(format!("`{program}' is missing"));
+        } else if !is_executable(&path)? {
+            problems.push(format!("`{program}' is not executable"));
+        }
+    }
+
+    Ok(problems)
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    let metadata = std::fs::metadata(path)
+        .with_context(|| anyhow!("Failed to read metadata of `{}'", path.display()))?;
+
+    Ok(metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> Result<bool> {
+    Ok(true)
+}
+
+/// Validate the staged toolchain and move it into the versions directory
+///
+/// A directory already present at the destination is a partial installation
+/// made by an older version of compact, and is replaced.
+pub async fn commit(
+    cfg: &CommandLineArguments,
+    staging: &Path,
+    version: &Version,
+    target: Target,
+) -> Result<()> {
+    validate(staging, version)?;
+
+    // the download isn't needed once extracted
+    for name in DOWNLOAD_FILES {
+        let path = staging.join(name);
+
+        if fs::symlink_metadata(&path).await.is_ok() {
+            remove_path(&path).await?;
+        }
+    }
+
+    let destination = install_dir(cfg, version, target);
+
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .with_context(|| anyhow!("Failed to create directory `{}'", parent.display()))?;
+    }
+
+    if fs::symlink_metadata(&destination).await.is_ok() {
+        remove_path(&destination).await?;
+    }
+
+    fs::rename(staging, &destination).await.with_context(|| {
+        anyhow!(
+            "Failed to move `{}' to `{}'",
+            staging.display(),
+            destination.display()
+        )
+    })
+}
+
+/// Remove an installed toolchain, and its version directory when no other
+/// target is left in it
+pub async fn uninstall(
+    cfg: &CommandLineArguments,
+    version: &Version,
+    target: Target,
+) -> Result<()> {
+    let dir = install_dir(cfg, version, target);
+
+    remove_path(&dir).await?;
+
+    if let Some(parent) = dir.parent() {
+        // fails when other targets are installed, which is expected
+        let _ = fs::remove_dir(parent).await;
+    }
+
+    Ok(())
+}
+
+async fn remove_path(path: &Path) -> Result<()> {
+    let metadata = fs::symlink_metadata(path)
+        .await
+        .with_context(|| anyhow!("Failed to read metadata of `{}'", path.display()))?;
+
+    let result = if metadata.is_dir() {
+        fs::remove_dir_all(path).await
+    } else {
+        fs::remove_file(path).await
+    };
+
+    result.with_context(|| anyhow!("Failed to remove `{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompactDirectory;
+
+    fn cfg(dir: &Path) -> CommandLineArguments {
+        CommandLineArguments::for_directory(CompactDirectory::from(dir.to_path_buf()))
+    }
+
+    fn write_program(dir: &Path, name: &str, mode: u32) {
+        let path = dir.join(name);
+        std::fs::write(&path, "#!/bin/sh\n").unwrap();
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt as _;
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+    }
+
+    #[tokio::test]
+    async fn install_from_staging() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path());
+        let version = Version::new(0, 29, 0);
+
+        let staging = prepare_staging(&cfg, &version, cfg.target).await.unwrap();
+        for program in ["compactc", "format-compact", "fixup-compact"] {
+            write_program(&staging, program, 0o755);
+        }
+        std::fs::write(staging.join("artifact.zip"), b"zip").unwrap();
+        assert!(!is_installed(&cfg, &version, cfg.target));
+
+        commit(&cfg, &staging, &version, cfg.target).await.unwrap();
+
+        assert!(is_installed(&cfg, &version, cfg.target));
+        assert!(!staging.exists());
+        assert!(
+            !install_dir(&cfg, &version, cfg.target)
+                .join("artifact.zip")
+                .exists()
+        );
+    }
+
+    #[tokio::test]
+    async fn uninstall_toolchain() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path());
+        let version = Version::new(0, 29, 0);
+
+        for target in [cfg.target, Target::Aarch64UnknownLinuxMusl] {
+            let staging = prepare_staging(&cfg, &version, target).await.unwrap();
+            for program in ["compactc", "format-compact", "fixup-compact"] {
+                write_program(&staging, program, 0o755);
+            }
+            commit(&cfg, &staging, &version, target).await.unwrap();
+        }
+
+        uninstall(&cfg, &version, cfg.target).await.unwrap();
+        assert!(!is_installed(&cfg, &version, cfg.target));
+        assert!(is_installed(
+            &cfg,
+            &version,
+            Target::Aarch64UnknownLinuxMusl
+        ));
+
+        uninstall(&cfg, &version, Target::Aarch64UnknownLinuxMusl)
+            .await
+            .unwrap();
+        assert!(!cfg.directory.versions_dir().join("0.29.0").exists());
+    }
+
+    #[tokio::test]
+    async fn incomplete_toolchain_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path());
+        let version = Version::new(0, 29, 0);
+
+        let staging = prepare_staging(&cfg, &version, cfg.target).await.unwrap();
+        write_program(&staging, "compactc", 0o755);
+
+        let error = commit(&cfg, &staging, &version, cfg.target)
+            .await
+            .unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid toolchain archive: `format-compact' is missing"
+        );
+        assert!(!is_installed(&cfg, &version, cfg.target));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn non_executable_program_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let version = Version::new(0, 24, 0);
+
+        write_program(dir.path(), "compactc", 0o644);
+
+        let error = validate(dir.path(), &version).unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            "Invalid toolchain archive: `compactc' is not executable"
+        );
+    }
+
+    #[tokio::test]
+    async fn interrupted_installations_are_cleaned() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path());
+        let version = Version::new(0, 29, 0);
+
+        // an interrupted installation of this version and of another one
+        let staging = staging_dir(&cfg, &version, cfg.target);
+        std::fs::create_dir_all(staging.join("lib")).unwrap();
+        std::fs::write(staging.join("artifact.zip.partial"), b"zip").unwrap();
+        write_program(&staging, "compactc", 0o755);
+
+        let other = staging_dir(&cfg, &Version::new(0, 28, 0), cfg.target);
+        std::fs::create_dir_all(&other).unwrap();
+
+        let prepared = prepare_staging(&cfg, &version, cfg.target).await.unwrap();
+
+        assert_eq!(prepared, staging);
+        assert!(!other.exists());
+        assert!(!staging.join("lib").exists());
+        assert!(!staging.join("compactc").exists());
+        assert!(staging.join("artifact.zip.partial").exists());
+    }
+}
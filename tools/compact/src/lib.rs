@@ -13,27 +13,42 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod archive;
+pub mod checksum;
 mod command_line_arguments;
 mod compact_directory;
 mod compiler;
 mod compiler_legacy;
+pub mod config;
 mod console;
+pub mod doctor;
 pub mod fetch;
 pub mod file;
 pub mod fixup;
 pub mod formatter;
 pub mod http;
+pub mod install;
+pub mod lock;
+pub mod output;
 pub mod progress;
+pub mod proxy;
+pub mod registry;
+pub mod reporter;
+pub mod retention;
+pub mod settings;
+pub mod signature;
+pub mod toolchain;
 pub mod utils;
 
 pub use self::{
     command_line_arguments::{
-        CheckCommand, CleanCommand, Command, CommandLineArguments, CompactUpdateConfig,
-        CompileCommand, FixupCommand, FormatCommand, ListCommand, SSelf, Target, UpdateCommand,
-        VersionSpec,
+        ChangelogCommand, CheckCommand, CleanCommand, Command, CommandLineArguments,
+        CompactUpdateConfig, CompileCommand, DefaultCommand, DoctorCommand, FixupCommand,
+        FormatCommand, ListCommand, SSelf, Target, UninstallCommand, UpdateCommand, VersionSpec,
     },
     compact_directory::CompactDirectory,
-    compiler::Compiler,
+    compiler::{Compiler, LAST_USED_FILE},
+    compiler_legacy::{SignatureCheck, Verification},
 };
 use semver::Version;
 use std::sync::LazyLock;
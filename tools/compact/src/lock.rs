@@ -0,0 +1,190 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Advisory locks shared between compact processes
+//!
+//! Several compact processes may use the same compact directory, for example
+//! parallel CI jobs. Installations, switches of the default toolchain and the
+//! cache of the release information are done while holding a lock file, so
+//! concurrent processes wait for each other instead of racing.
+//!
+//! Locks are always taken in the same order to avoid deadlocks: installation,
+//! then cache, then settings.
+
+use crate::CommandLineArguments;
+use anyhow::{Context, Result, anyhow, bail};
+use fs4::fs_std::FileExt as _;
+use std::{
+    fs::{File, OpenOptions},
+    io::Write as _,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Lock serialising the installation and removal of toolchains
+pub const INSTALL_LOCK: &str = "install.lock";
+
+/// Lock serialising the changes of the settings
+pub const SETTINGS_LOCK: &str = "settings.lock";
+
+/// An exclusive lock, released when dropped
+#[derive(Debug)]
+pub struct Lock {
+    // the lock is held as long as the file is open
+    _file: File,
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Acquire the lock file `path`, waiting up to the lock timeout for the
+    /// process holding it
+    pub async fn acquire(cfg: &CommandLineArguments, path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let timeout = Duration::from_secs(cfg.lock_timeout);
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| anyhow!("Failed to create directory `{}'", parent.display()))?;
+        }
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| anyhow!("Failed to open lock file `{}'", path.display()))?;
+
+        let start = Instant::now();
+        let mut waiting = false;
+
+        loop {
+            let locked = file
+                .try_lock_exclusive()
+                .with_context(|| anyhow!("Failed to lock `{}'", path.display()))?;
+
+            if locked {
+                // record who holds the lock for the processes waiting on it
+                file.set_len(0)
+                    .and_then(|()| writeln!(file, "{}", std::process::id()))
+                    .with_context(|| anyhow!("Failed to write lock file `{}'", path.display()))?;
+
+                return Ok(Self { _file: file, path });
+            }
+
+            let holder = holder(&path);
+
+            if start.elapsed() >= timeout {
+                bail!(
+                    "Timed out after {}s waiting for lock `{}' held by {holder}",
+                    timeout.as_secs(),
+                    path.display(),
+                );
+            }
+
+            if !waiting {
+                eprintln!(
+                    "{label}: waiting for lock held by {holder}",
+                    label = cfg.style.label(),
+                );
+                waiting = true;
+            }
+
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    /// Acquire the lock `name` of the compact directory
+    pub async fn directory(cfg: &CommandLineArguments, name: &str) -> Result<Self> {
+        Self::acquire(cfg, cfg.directory.join(name)).await
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Describe the process holding the lock file `path`
+fn holder(path: &Path) -> String {
+    std::fs::read_to_string(path)
+        .ok()
+        .and_then(|content| content.trim().parse::<u32>().ok())
+        .map(|pid| format!("PID {pid}"))
+        .unwrap_or_else(|| "another process".to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CompactDirectory;
+
+    fn cfg(dir: &Path, lock_timeout: u64) -> CommandLineArguments {
+        CommandLineArguments {
+            lock_timeout,
+            ..CommandLineArguments::for_directory(CompactDirectory::from(dir.to_path_buf()))
+        }
+    }
+
+    #[tokio::test]
+    async fn lock_records_pid() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path(), 0);
+
+        let lock = Lock::directory(&cfg, INSTALL_LOCK).await.unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(lock.path()).unwrap(),
+            format!("{}\n", std::process::id())
+        );
+    }
+
+    #[tokio::test]
+    async fn held_lock_times_out() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path(), 0);
+
+        let lock = Lock::directory(&cfg, INSTALL_LOCK).await.unwrap();
+        let error = Lock::directory(&cfg, INSTALL_LOCK).await.unwrap_err();
+
+        assert_eq!(
+            error.to_string(),
+            format!(
+                "Timed out after 0s waiting for lock `{}' held by PID {}",
+                lock.path().display(),
+                std::process::id()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn released_lock_is_acquired() {
+        let dir = tempfile::tempdir().unwrap();
+        let cfg = cfg(dir.path(), 5);
+
+        let lock = Lock::directory(&cfg, INSTALL_LOCK).await.unwrap();
+
+        let waiter = {
+            let cfg = cfg.clone();
+            tokio::spawn(async move { Lock::directory(&cfg, INSTALL_LOCK).await })
+        };
+
+        tokio::time::sleep(POLL_INTERVAL * 3).await;
+        drop(lock);
+
+        assert!(waiter.await.unwrap().is_ok());
+    }
+}
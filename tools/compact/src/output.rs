@@ -0,0 +1,339 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Machine-readable output of the commands
+//!
+//! With `--output json` the commands print one JSON document on the standard
+//! output instead of the styled text. Progress, warnings and diagnostics are
+//! still written to the standard error.
+
+use crate::Target;
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use semver::Version;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// Format of the output of the commands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum OutputFormat {
+    /// Human readable, styled text
+    #[default]
+    Text,
+    /// A JSON document
+    Json,
+}
+
+impl OutputFormat {
+    pub fn is_json(self) -> bool {
+        self == Self::Json
+    }
+}
+
+/// Result of `compact check`
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    /// The default toolchain, if any
+    pub current: Option<Toolchain>,
+    /// The toolchain pinned by the project, if any
+    pub project: Option<ProjectToolchain>,
+    /// The newest version, matching the project pin if there is one
+    pub latest: Version,
+    pub update_available: bool,
+    pub status: CheckStatus,
+}
+
+/// A toolchain pinned by `compact-toolchain.toml` or `COMPACT_TOOLCHAIN`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectToolchain {
+    /// The requested version specifier
+    pub spec: String,
+    /// The toolchain file, or the environment variable
+    pub source: String,
+    /// The newest installed version matching the specifier
+    pub version: Option<Version>,
+}
+
+/// Outcome of `compact check`, also reflected in its exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CheckStatus {
+    UpToDate,
+    UpdateAvailable,
+    NoToolchain,
+    PinMismatch,
+}
+
+impl CheckStatus {
+    /// Exit status of `compact check`
+    ///
+    /// An available update is only an error with `--fail-on-outdated`:
+    /// `compact check` always succeeded before, and scripts running it with
+    /// `set -e` must not break whenever a new version is released.
+    pub fn exit_code(self, fail_on_outdated: bool) -> i32 {
+        match self {
+            Self::UpToDate => 0,
+            Self::UpdateAvailable if fail_on_outdated => 10,
+            Self::UpdateAvailable => 0,
+            Self::NoToolchain => 11,
+            Self::PinMismatch => 12,
+        }
+    }
+}
+
+/// An installed toolchain
+#[derive(Debug, Clone, Serialize)]
+pub struct Toolchain {
+    pub version: Version,
+    pub target: Target,
+}
+
+/// Result of `compact list`
+#[derive(Debug, Clone, Serialize)]
+pub struct ListReport {
+    pub versions: Vec<AvailableVersion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AvailableVersion {
+    pub version: Version,
+    /// Platforms the version is published for
+    pub platforms: Vec<&'static str>,
+    /// Whether this is the default toolchain
+    pub default: bool,
+}
+
+/// Result of `compact list --installed`
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledReport {
+    pub versions: Vec<InstalledVersion>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct InstalledVersion {
+    /// Name of the version directory, normally a version
+    pub version: String,
+    /// Whether this is the default toolchain
+    pub default: bool,
+}
+
+/// Result of `compact changelog`
+#[derive(Debug, Clone, Serialize)]
+pub struct ChangelogReport {
+    /// Newest release first
+    pub releases: Vec<ReleaseNotes>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReleaseNotes {
+    pub version: Version,
+    pub notes: Option<String>,
+}
+
+/// Result of `compact update`
+#[derive(Debug, Clone, Serialize)]
+pub struct UpdateReport {
+    pub version: Version,
+    pub target: Target,
+    pub status: UpdateStatus,
+    /// Whether the version was made the default toolchain
+    pub default: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    Installed,
+    AlreadyInstalled,
+}
+
+/// Result of `compact uninstall`
+#[derive(Debug, Clone, Serialize)]
+pub struct UninstallReport {
+    pub version: Version,
+    pub target: Target,
+    /// The default toolchain after the removal, if any
+    pub default: Option<Toolchain>,
+    /// The removed version was the default one
+    pub default_changed: bool,
+}
+
+/// Result of `compact clean`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanReport {
+    /// Removed versions
+    pub removed: Vec<String>,
+    /// Kept versions
+    pub kept: Vec<String>,
+    /// Removed cache files
+    pub cache: Vec<PathBuf>,
+    /// Nothing was removed, `removed` and `cache` list what would be
+    pub dry_run: bool,
+    /// Size of the removed versions, in bytes
+    pub reclaimed: u64,
+}
+
+/// Result of `compact doctor`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DoctorReport {
+    pub checks: Vec<Diagnosis>,
+}
+
+impl DoctorReport {
+    /// Whether problems are left, which `compact doctor` exits with
+    pub fn failed(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| check.status == DiagnosisStatus::Fail)
+    }
+}
+
+/// Outcome of one check of `compact doctor`
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnosis {
+    pub check: &'static str,
+    pub status: DiagnosisStatus,
+    /// What was checked, or found
+    pub summary: String,
+    /// The problems found, or repaired with `--fix`
+    pub problems: Vec<String>,
+    /// How to repair the problems
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosisStatus {
+    Pass,
+    /// Problems that don't prevent compact from working
+    Warn,
+    Fail,
+    /// Problems repaired with `--fix`
+    Fixed,
+}
+
+/// Result of `compact format` and `compact fixup`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FilesReport {
+    pub files: Vec<FileReport>,
+    /// Whether every file was processed and, with `--check`, is unchanged
+    pub success: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    pub path: PathBuf,
+    pub status: FileStatus,
+    /// With `--check`, the changes the file needs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileStatus {
+    /// The file was rewritten
+    Changed,
+    Unchanged,
+    /// With `--check`, the file would be rewritten
+    NeedsChanges,
+    Failed,
+}
+
+/// Result of `compact self check` and `compact self update`
+#[derive(Debug, Clone, Serialize)]
+pub struct SelfReport {
+    pub name: &'static str,
+    pub current: Version,
+    /// The newest version, if newer than the current one
+    pub latest: Option<Version>,
+    /// Whether `compact self update` installed the newest version
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub updated: Option<bool>,
+}
+
+/// Print `report` as a JSON document on the standard output
+pub fn print_json<T: Serialize>(report: &T) -> Result<()> {
+    let json = serde_json::to_string_pretty(report).context("Failed to serialize the output")?;
+
+    println!("{json}");
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serialize_update() {
+        let report = UpdateReport {
+            version: Version::new(0, 29, 0),
+            target: Target::x86_64UnknownLinuxMusl,
+            status: UpdateStatus::AlreadyInstalled,
+            default: true,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "version": "0.29.0",
+                "target": "x86_64-unknown-linux-musl",
+                "status": "already_installed",
+                "default": true,
+            })
+        );
+    }
+
+    #[test]
+    fn check_exit_codes() {
+        assert_eq!(CheckStatus::UpToDate.exit_code(true), 0);
+        assert_eq!(CheckStatus::UpdateAvailable.exit_code(false), 0);
+        assert_eq!(CheckStatus::UpdateAvailable.exit_code(true), 10);
+        assert_eq!(CheckStatus::NoToolchain.exit_code(false), 11);
+        assert_eq!(CheckStatus::PinMismatch.exit_code(false), 12);
+    }
+
+    #[test]
+    fn serialize_files() {
+        let report = FilesReport {
+            files: vec![
+                FileReport {
+                    path: "a.compact".into(),
+                    status: FileStatus::Unchanged,
+                    diff: None,
+                },
+                FileReport {
+                    path: "b.compact".into(),
+                    status: FileStatus::NeedsChanges,
+                    diff: Some("-a\n+b\n".to_owned()),
+                },
+            ],
+            success: false,
+        };
+
+        assert_eq!(
+            serde_json::to_value(&report).unwrap(),
+            serde_json::json!({
+                "files": [
+                    { "path": "a.compact", "status": "unchanged" },
+                    { "path": "b.compact", "status": "needs_changes", "diff": "-a\n+b\n" },
+                ],
+                "success": false,
+            })
+        );
+    }
+}
@@ -13,11 +13,14 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, time::Duration};
 
 use anyhow::Result;
 use futures::Future;
 use indicatif::{ProgressBar, ProgressFinish, ProgressStyle};
+use tokio::time::Instant;
+
+use crate::reporter::{ProgressMode, Reporter};
 
 pub enum Poll<T> {
     Done(T),
@@ -51,7 +54,30 @@ const PROGRESS_TEMPLATE: &str =
 const SPINNER_TEMPLATE: &str = "[{elapsed_precise}] {spinner} {msg}";
 const INDICATIF_LENGTH: u64 = 100;
 
+/// Time between two lines of plain progress, tasks finishing sooner don't
+/// print any
+const PLAIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// A spinner, drawn only when the progress is animated
+pub fn spinner() -> ProgressBar {
+    match Reporter::current().progress {
+        ProgressMode::Animated => ProgressBar::new_spinner(),
+        ProgressMode::Plain | ProgressMode::Hidden => ProgressBar::hidden(),
+    }
+}
+
 pub async fn future<F, O>(msg: impl Into<Cow<'static, str>>, task: F) -> Result<O>
+where
+    F: Future<Output = Result<O>>,
+{
+    match Reporter::current().progress {
+        ProgressMode::Animated => animated_future(msg, task).await,
+        ProgressMode::Plain => plain_future(msg.into(), task).await,
+        ProgressMode::Hidden => task.await,
+    }
+}
+
+async fn animated_future<F, O>(msg: impl Into<Cow<'static, str>>, task: F) -> Result<O>
 where
     F: Future<Output = Result<O>>,
 {
@@ -74,7 +100,36 @@ where
     output
 }
 
+async fn plain_future<F, O>(msg: Cow<'static, str>, task: F) -> Result<O>
+where
+    F: Future<Output = Result<O>>,
+{
+    let start = Instant::now();
+    let mut interval = tokio::time::interval_at(start + PLAIN_INTERVAL, PLAIN_INTERVAL);
+
+    tokio::pin!(task);
+
+    loop {
+        tokio::select! {
+            output = &mut task => return output,
+            _ = interval.tick() => eprintln!("{msg} ({}s)", start.elapsed().as_secs()),
+        }
+    }
+}
+
 pub async fn progress<TASK: HasLength>(mut task: TASK) -> Result<TASK::Output> {
+    match Reporter::current().progress {
+        ProgressMode::Animated => animated_progress(task).await,
+        ProgressMode::Plain => plain_progress(task).await,
+        ProgressMode::Hidden => loop {
+            if let Poll::Done(t) = task.tick().await? {
+                return Ok(t);
+            }
+        },
+    }
+}
+
+async fn animated_progress<TASK: HasLength>(mut task: TASK) -> Result<TASK::Output> {
     let style = ProgressStyle::default_bar().template(PROGRESS_TEMPLATE)?;
     let progress = ProgressBar::new(INDICATIF_LENGTH)
         .with_style(style)
@@ -103,3 +158,60 @@ pub async fn progress<TASK: HasLength>(mut task: TASK) -> Result<TASK::Output> {
         }
     }
 }
+
+async fn plain_progress<TASK: HasLength>(mut task: TASK) -> Result<TASK::Output> {
+    let msg: Cow<'static, str> = task.msg().into();
+    let mut last = Instant::now();
+    let mut reported = false;
+
+    loop {
+        let poll = task.tick().await?;
+        let done = matches!(poll, Poll::Done(_));
+
+        // tasks reported at least once also report their end
+        if last.elapsed() >= PLAIN_INTERVAL || (done && reported) {
+            let position = plain_position(task.current_length(), task.expected_length());
+            let end = if done { ", done" } else { "" };
+
+            eprintln!("{msg}: {position}{end}");
+
+            last = Instant::now();
+            reported = true;
+        }
+
+        if let Poll::Done(t) = poll {
+            return Ok(t);
+        }
+    }
+}
+
+/// Plain text position of a task, like `40/120 MiB`
+fn plain_position(current: u64, total: Option<u64>) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+
+    let largest = total.unwrap_or(current).max(current);
+    let exponent = (0..UNITS.len())
+        .rev()
+        .find(|&exponent| largest >= 1 << (10 * exponent))
+        .unwrap_or(0);
+    let scale = |bytes: u64| bytes >> (10 * exponent);
+
+    match total {
+        Some(total) => format!("{}/{} {}", scale(current), scale(total), UNITS[exponent]),
+        None => format!("{} {}", scale(current), UNITS[exponent]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_positions() {
+        assert_eq!(plain_position(40 << 20, Some(120 << 20)), "40/120 MiB");
+        assert_eq!(plain_position(512, Some(2048)), "0/2 KiB");
+        assert_eq!(plain_position(12, None), "12 B");
+        assert_eq!(plain_position(3 << 30, None), "3 GiB");
+        assert_eq!(plain_position(0, Some(0)), "0/0 B");
+    }
+}
@@ -0,0 +1,252 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Toolchain proxies
+//!
+//! The `bin` directory of the compact directory contains one executable per
+//! toolchain program. Each of them is a copy of (or a hard link to) the
+//! `compact` executable itself: when invoked under one of these names,
+//! `compact` selects the toolchain version at run time and forwards the
+//! invocation to the program of that toolchain.
+//!
+//! The version is selected from, in order:
+//!
+//! 1. a `+VERSION` first argument;
+//! 2. the `COMPACT_TOOLCHAIN` environment variable;
+//! 3. the closest `compact-toolchain.toml` file;
+//! 4. the default toolchain.
+//!
+//! The environment variable is checked before the toolchain file, as in
+//! `compact compile`: it is meant to override the project pin for a single
+//! run, which it could not do if the file took precedence.
+
+use crate::{
+    CommandLineArguments, Compiler, VersionSpec,
+    compact_directory::{COMPACTUP_VERSIONS_DIR, CompactDirectory},
+    reporter, toolchain, utils,
+};
+use anyhow::{Context, Result, anyhow, ensure};
+use std::{
+    ffi::{OsStr, OsString},
+    path::PathBuf,
+};
+use tokio::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Proxy {
+    Compactc,
+    FormatCompact,
+    FixupCompact,
+}
+
+impl Proxy {
+    pub const ALL: [Self; 3] = [Self::Compactc, Self::FormatCompact, Self::FixupCompact];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Compactc => "compactc",
+            Self::FormatCompact => "format-compact",
+            Self::FixupCompact => "fixup-compact",
+        }
+    }
+
+    /// Recognise a proxy from the name the program was invoked with
+    pub fn from_program(program: &OsStr) -> Option<Self> {
+        let name = PathBuf::from(program);
+        let name = name.file_stem()?;
+
+        Self::ALL.into_iter().find(|proxy| name == proxy.name())
+    }
+
+    /// Path of the program forwarded to in the given toolchain
+    pub fn program(self, compiler: &Compiler) -> PathBuf {
+        match self {
+            Self::Compactc => compiler.path_compactc().to_path_buf(),
+            Self::FormatCompact => compiler.path_format_compact(),
+            Self::FixupCompact => compiler.path_fixup_compact(),
+        }
+    }
+
+    pub(crate) fn file_name(self) -> String {
+        format!("{}{}", self.name(), std::env::consts::EXE_SUFFIX)
+    }
+}
+
+/// Install the proxies in the `bin` directory, replacing whatever was there
+pub async fn install_proxies(cfg: &CommandLineArguments) -> Result<()> {
+    let exe = std::env::current_exe().context("Failed to locate the compact executable")?;
+
+    for proxy in Proxy::ALL {
+        let target = cfg.directory.bin_dir().join(proxy.file_name());
+
+        remove_proxy(&target).await?;
+
+        if fs::hard_link(&exe, &target).await.is_ok() {
+            tracing::debug!(proxy = %target.display(), exe = %exe.display(), "proxy linked");
+        } else {
+            fs::copy(&exe, &target)
+                .await
+                .with_context(|| anyhow!("Failed to install proxy {target:?}"))?;
+            tracing::debug!(proxy = %target.display(), exe = %exe.display(), "proxy copied");
+        }
+    }
+
+    Ok(())
+}
+
+/// Remove the proxies from the `bin` directory
+pub async fn remove_proxies(cfg: &CommandLineArguments) -> Result<()> {
+    for proxy in Proxy::ALL {
+        remove_proxy(&cfg.directory.bin_dir().join(proxy.file_name())).await?;
+    }
+
+    Ok(())
+}
+
+async fn remove_proxy(target: &PathBuf) -> Result<()> {
+    // `symlink_metadata` so dangling symbolic links left by older versions
+    // of compact are removed too
+    if fs::symlink_metadata(target).await.is_ok() {
+        fs::remove_file(target)
+            .await
+            .with_context(|| anyhow!("Failed to remove previous proxy {target:?}"))?;
+        tracing::debug!(proxy = %target.display(), "proxy removed");
+    }
+
+    Ok(())
+}
+
+/// Run the toolchain program behind `proxy` with the given arguments
+pub async fn run(proxy: Proxy, mut args: Vec<OsString>) -> Result<()> {
+    // the arguments are the program's, only `COMPACT_LOG` selects the traces
+    reporter::init_tracing(0);
+
+    let cfg = CommandLineArguments::for_directory(proxy_directory()?);
+
+    let spec = match args.first().and_then(|arg| arg.to_str()) {
+        Some(arg) if arg.starts_with('+') => {
+            let spec: VersionSpec = arg[1..].parse().context("Invalid version format")?;
+            args.remove(0);
+            Some(spec)
+        }
+        _ => None,
+    };
+
+    let compiler = match spec {
+        Some(spec) => {
+            let version = toolchain::find_installed(&cfg, &spec)
+                .await?
+                .ok_or_else(|| {
+                    anyhow!("Toolchain {spec} is not installed, try `compact update {spec}'")
+                })?;
+
+            Compiler::open(&cfg, version, cfg.target).await?
+        }
+        None => toolchain::active_compiler(&cfg)
+            .await
+            .context("Failed to load current compiler.")?
+            .ok_or_else(|| anyhow!("No default compiler set"))?,
+    };
+
+    compiler.mark_used();
+
+    let program = proxy.program(&compiler);
+    tracing::debug!(proxy = proxy.name(), version = %compiler.version(), "toolchain selected");
+
+    ensure!(
+        program.is_file(),
+        "{name} is not available in toolchain {version}",
+        name = proxy.name(),
+        version = compiler.version(),
+    );
+
+    exec(program, args)
+}
+
+/// The compact directory a proxy belongs to
+///
+/// Proxies live in `<compact directory>/bin`, this is preferred over the
+/// environment so a proxy always runs a toolchain of its own directory.
+fn proxy_directory() -> Result<CompactDirectory> {
+    let exe = std::env::current_exe().context("Failed to locate the proxy executable")?;
+
+    if let Some(bin) = exe.parent()
+        && bin.file_name() == Some(OsStr::new("bin"))
+        && let Some(directory) = bin.parent()
+        && directory.join(COMPACTUP_VERSIONS_DIR).is_dir()
+    {
+        return Ok(CompactDirectory::from(directory.to_path_buf()));
+    }
+
+    match std::env::var_os("COMPACT_DIRECTORY") {
+        Some(directory) if !directory.is_empty() => {
+            Ok(CompactDirectory::from(PathBuf::from(directory)))
+        }
+        _ => Ok(CompactDirectory::default()),
+    }
+}
+
+#[cfg(unix)]
+fn exec(program: PathBuf, args: Vec<OsString>) -> Result<()> {
+    use std::os::unix::process::CommandExt as _;
+
+    let mut command = std::process::Command::new(&program);
+    command.args(args);
+
+    utils::trace_spawn(&command);
+    let error = command.exec();
+
+    Err(error).with_context(|| anyhow!("Failed to execute {program:?}"))
+}
+
+#[cfg(not(unix))]
+fn exec(program: PathBuf, args: Vec<OsString>) -> Result<()> {
+    let mut command = std::process::Command::new(&program);
+    command.args(args);
+
+    let start = utils::trace_spawn(&command);
+    let status = command.status();
+    utils::trace_exit(&command, start, status.as_ref().copied());
+
+    let status = status.with_context(|| anyhow!("Failed to execute {program:?}"))?;
+
+    std::process::exit(status.code().unwrap_or(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognise_proxies() {
+        assert_eq!(
+            Proxy::from_program(OsStr::new("compactc")),
+            Some(Proxy::Compactc)
+        );
+        assert_eq!(
+            Proxy::from_program(OsStr::new("/home/user/.compact/bin/format-compact")),
+            Some(Proxy::FormatCompact)
+        );
+        assert_eq!(
+            Proxy::from_program(OsStr::new("fixup-compact.exe")),
+            Some(Proxy::FixupCompact)
+        );
+        assert_eq!(Proxy::from_program(OsStr::new("compact")), None);
+        assert_eq!(
+            Proxy::from_program(OsStr::new("/usr/bin/compactc-old")),
+            None
+        );
+    }
+}
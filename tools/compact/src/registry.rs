@@ -0,0 +1,261 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Releases served from a local directory
+//!
+//! A registry is a directory, or a `file://` URL, laid out like the mirrors
+//! with an `index.json` describing the releases:
+//!
+//! ```text
+//! registry/
+//! ├── index.json
+//! └── compactc-v0.29.0/
+//!     ├── compactc_v0.29.0_x86_64-unknown-linux-musl.zip
+//!     └── SHA256SUMS
+//! ```
+//!
+//! ```json
+//! {
+//!   "releases": [
+//!     {
+//!       "tag_name": "compactc-v0.29.0",
+//!       "body": "Release notes",
+//!       "assets": [
+//!         { "name": "compactc_v0.29.0_x86_64-unknown-linux-musl.zip", "digest": "sha256:..." },
+//!         { "name": "SHA256SUMS" }
+//!       ]
+//!     }
+//!   ]
+//! }
+//! ```
+
+use crate::fetch::{self, MidnightCompiler, Release, ReleaseAsset};
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::Url;
+use semver::Version;
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Component, Path},
+};
+
+pub const INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Deserialize)]
+struct Index {
+    releases: Vec<IndexRelease>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IndexRelease {
+    tag_name: String,
+    /// Release notes
+    #[serde(default)]
+    body: Option<String>,
+    #[serde(default)]
+    assets: Vec<IndexAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct IndexAsset {
+    name: String,
+    #[serde(default)]
+    size: u64,
+    #[serde(default)]
+    digest: Option<String>,
+}
+
+/// Parse a registry given as a directory or as a `file://` URL
+pub fn parse_registry(registry: &str) -> Result<Url> {
+    let mut url = if registry.contains("://") {
+        let url =
+            Url::parse(registry).with_context(|| anyhow!("Invalid registry URL `{registry}'"))?;
+
+        if url.scheme() != "file" {
+            bail!("Invalid registry `{registry}', expected a directory or a `file://' URL");
+        }
+
+        url
+    } else {
+        let path = std::path::absolute(Path::new(registry))
+            .with_context(|| anyhow!("Invalid registry path `{registry}'"))?;
+
+        Url::from_directory_path(&path)
+            .map_err(|()| anyhow!("Invalid registry path `{registry}'"))?
+    };
+
+    // the assets are resolved relative to the registry
+    if !url.path().ends_with('/') {
+        url.set_path(&format!("{}/", url.path()));
+    }
+
+    Ok(url)
+}
+
+/// Load the releases described by the index of the registry
+pub async fn load(registry: &Url) -> Result<BTreeMap<Version, MidnightCompiler>> {
+    let index_url = registry.join(INDEX_FILE)?;
+    let index_path = index_url
+        .to_file_path()
+        .map_err(|()| anyhow!("Invalid registry `{registry}'"))?;
+
+    let content = tokio::fs::read_to_string(&index_path)
+        .await
+        .with_context(|| anyhow!("Failed to read the registry index `{index_path:?}'"))?;
+
+    let index: Index = serde_json::from_str(&content)
+        .with_context(|| anyhow!("Invalid registry index `{index_path:?}'"))?;
+
+    let mut output = BTreeMap::new();
+
+    for release in index.releases {
+        check_path_component(&release.tag_name)?;
+
+        let assets = release
+            .assets
+            .into_iter()
+            .map(|asset| {
+                check_path_component(&asset.name)?;
+
+                let browser_download_url = registry
+                    .join(&format!("{}/{}", release.tag_name, asset.name))
+                    .with_context(|| anyhow!("Invalid asset name `{}'", asset.name))?;
+
+                Ok(ReleaseAsset {
+                    name: asset.name,
+                    browser_download_url,
+                    size: asset.size,
+                    digest: asset.digest,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        let release = Release {
+            tag_name: release.tag_name,
+            body: release.body,
+            assets,
+        };
+
+        if release.tag_name.contains("compactc") {
+            let compiler = fetch::load_compiler_version(release).await?;
+            output.insert(compiler.version.clone(), compiler);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Refuse the names of the index that are not a single plain path component,
+/// so the URLs built from them stay within the release directories
+fn check_path_component(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    let plain = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(_)), None)
+    ) && !name.contains(['/', '\\', '%', '?', '#']);
+
+    if !plain {
+        bail!("Refusing registry entry `{name}', which is not a plain file name");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let url = parse_registry("/srv/compact").unwrap();
+        assert_eq!(url.as_str(), "file:///srv/compact/");
+
+        let url = parse_registry("file:///srv/compact/").unwrap();
+        assert_eq!(url.as_str(), "file:///srv/compact/");
+
+        assert!(parse_registry("https://example.com/compact").is_err());
+    }
+
+    #[tokio::test]
+    async fn load_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join(INDEX_FILE),
+            r#"{
+              "releases": [
+                {
+                  "tag_name": "compactc-v0.29.0",
+                  "assets": [
+                    { "name": "compactc_v0.29.0_x86_64-unknown-linux-musl.zip", "size": 3 },
+                    { "name": "SHA256SUMS" }
+                  ]
+                },
+                { "tag_name": "compact-v0.2.0" }
+              ]
+            }"#,
+        )
+        .unwrap();
+
+        let registry = parse_registry(dir.path().to_str().unwrap()).unwrap();
+        let compilers = load(&registry).await.unwrap();
+
+        assert_eq!(compilers.len(), 1);
+
+        let compiler = &compilers[&Version::new(0, 29, 0)];
+        let asset = compiler.x86_linux.as_ref().unwrap();
+        assert_eq!(
+            asset.browser_download_url,
+            registry
+                .join("compactc-v0.29.0/compactc_v0.29.0_x86_64-unknown-linux-musl.zip")
+                .unwrap()
+        );
+        assert_eq!(compiler.checksums.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reject_unsafe_names() {
+        for (tag_name, name) in [
+            ("compactc-v0.29.0", "../../secret.zip"),
+            ("compactc-v0.29.0", "sub/compactc.zip"),
+            ("compactc-v0.29.0", "..\\compactc.zip"),
+            ("compactc-v0.29.0", "%2e%2e"),
+            ("compactc-v0.29.0", ".."),
+            ("compactc-v0.29.0", "/etc/passwd"),
+            ("..", "compactc.zip"),
+        ] {
+            let dir = tempfile::tempdir().unwrap();
+            std::fs::write(
+                dir.path().join(INDEX_FILE),
+                serde_json::json!({
+                    "releases": [{ "tag_name": tag_name, "assets": [{ "name": name }] }]
+                })
+                .to_string(),
+            )
+            .unwrap();
+
+            let registry = parse_registry(dir.path().to_str().unwrap()).unwrap();
+
+            assert!(load(&registry).await.is_err(), "{tag_name}/{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = parse_registry(dir.path().to_str().unwrap()).unwrap();
+
+        assert!(load(&registry).await.is_err());
+    }
+}
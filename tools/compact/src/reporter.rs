@@ -0,0 +1,195 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! How much, and how, the command line tool reports what it does
+//!
+//! The [`Reporter`] of the process is installed once the command line
+//! arguments are parsed. It sets up the colours of the output and the
+//! diagnostic traces, and tells the [`progress`](crate::progress) functions
+//! how to draw.
+
+use std::{ffi::OsStr, sync::OnceLock};
+
+use clap::ValueEnum;
+use console::Term;
+use tracing_subscriber::{EnvFilter, fmt::time};
+
+use crate::CommandLineArguments;
+
+/// When to colour the output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum ColorChoice {
+    /// Colour the output of terminals, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    /// Always colour the output
+    Always,
+    /// Never colour the output
+    Never,
+}
+
+impl ColorChoice {
+    /// Colouring imposed by the choice and the value of `NO_COLOR`, `None`
+    /// leaving it to the terminal
+    fn forced(self, no_color: Option<&OsStr>) -> Option<bool> {
+        match self {
+            Self::Always => Some(true),
+            Self::Never => Some(false),
+            Self::Auto if no_color.is_some_and(|value| !value.is_empty()) => Some(false),
+            Self::Auto => None,
+        }
+    }
+}
+
+/// How progress of long running tasks is reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressMode {
+    /// Progress bars and spinners redrawn in place, for terminals
+    Animated,
+    /// Periodic lines of plain text, for logs
+    Plain,
+    /// Nothing is reported
+    Hidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reporter {
+    /// Only errors, warnings and the requested data are printed
+    pub quiet: bool,
+    pub progress: ProgressMode,
+}
+
+static REPORTER: OnceLock<Reporter> = OnceLock::new();
+
+/// Environment variable selecting the diagnostic traces, with the syntax of
+/// [`EnvFilter`], e.g. `debug` or `compact::http=trace`
+pub const LOG_ENV: &str = "COMPACT_LOG";
+
+impl Reporter {
+    /// Progress bars are not animated while traces are printed, they would
+    /// be redrawn over each other
+    pub fn new(quiet: bool, tracing: bool) -> Self {
+        let progress = if quiet {
+            ProgressMode::Hidden
+        } else if Term::stderr().is_term() && !tracing {
+            ProgressMode::Animated
+        } else {
+            ProgressMode::Plain
+        };
+
+        Self { quiet, progress }
+    }
+
+    /// Install the reporter of the process and set up the colours of the
+    /// output and the diagnostic traces
+    pub fn init(cfg: &CommandLineArguments) -> Self {
+        if let Some(enabled) = cfg.color.forced(std::env::var_os("NO_COLOR").as_deref()) {
+            console::set_colors_enabled(enabled);
+            console::set_colors_enabled_stderr(enabled);
+        }
+
+        let tracing = init_tracing(cfg.verbose);
+
+        *REPORTER.get_or_init(|| Self::new(cfg.quiet, tracing))
+    }
+
+    /// The reporter of the process, reporting everything when none was
+    /// installed
+    pub fn current() -> Self {
+        REPORTER
+            .get()
+            .copied()
+            .unwrap_or_else(|| Self::new(false, false))
+    }
+}
+
+/// Traces selected by `COMPACT_LOG`, or else by the `--verbose` count
+///
+/// A single `-v` prints no traces: it keeps its meaning for `format` and
+/// `fixup`, whose output is relied upon.
+fn log_filter(log: Option<&str>, verbose: u8) -> Option<EnvFilter> {
+    let directives = match (log, verbose) {
+        (Some(log), _) if !log.is_empty() => log,
+        (_, 0 | 1) => return None,
+        (_, 2) => "compact=debug",
+        (_, _) => "compact=trace,reqwest=debug",
+    };
+
+    match EnvFilter::try_new(directives) {
+        Ok(filter) => Some(filter),
+        Err(error) => {
+            eprintln!("Warning: Invalid {LOG_ENV} `{directives}': {error}");
+            None
+        }
+    }
+}
+
+/// Print the diagnostic traces selected by `COMPACT_LOG` or `verbose` on the
+/// standard error, returns whether any are
+pub fn init_tracing(verbose: u8) -> bool {
+    let log = std::env::var(LOG_ENV).ok();
+
+    let Some(filter) = log_filter(log.as_deref(), verbose) else {
+        return false;
+    };
+
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(console::colors_enabled_stderr())
+        .with_timer(time::uptime())
+        .try_init()
+        .is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn color_choice() {
+        assert_eq!(ColorChoice::Auto.forced(None), None);
+        assert_eq!(ColorChoice::Auto.forced(Some(OsStr::new(""))), None);
+        assert_eq!(ColorChoice::Auto.forced(Some(OsStr::new("1"))), Some(false));
+        assert_eq!(
+            ColorChoice::Always.forced(Some(OsStr::new("1"))),
+            Some(true)
+        );
+        assert_eq!(ColorChoice::Never.forced(None), Some(false));
+    }
+
+    #[test]
+    fn quiet_hides_progress() {
+        assert_eq!(Reporter::new(true, false).progress, ProgressMode::Hidden);
+        assert_ne!(Reporter::new(false, false).progress, ProgressMode::Hidden);
+        assert_ne!(Reporter::new(false, true).progress, ProgressMode::Animated);
+    }
+
+    #[test]
+    fn log_filters() {
+        let filter = |log, verbose| log_filter(log, verbose).map(|filter| filter.to_string());
+
+        assert_eq!(filter(None, 0), None);
+        assert_eq!(filter(Some(""), 0), None);
+        assert_eq!(filter(None, 1), None);
+        assert_eq!(filter(None, 2).as_deref(), Some("compact=debug"));
+        assert_eq!(
+            filter(None, 3),
+            Some(EnvFilter::new("compact=trace,reqwest=debug").to_string())
+        );
+        assert_eq!(filter(Some("info"), 1).as_deref(), Some("info"));
+        assert_eq!(filter(Some("compact=nonsense"), 0), None);
+    }
+}
@@ -0,0 +1,290 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Retention policies of `compact clean`
+//!
+//! Without any policy `compact clean` removes every installed version. The
+//! policies select the versions to keep instead:
+//!
+//! * `--keep-latest N` keeps the N newest versions,
+//! * `--keep SPEC` keeps the versions matching a version specifier,
+//! * `--older-than AGE` keeps the versions installed less than AGE ago,
+//! * `--unused-since AGE` keeps the versions used less than AGE ago.
+//!
+//! The versions given as protected, the default one with `--keep-current` and
+//! the one pinned by the current project, are always kept.
+
+use crate::{CleanCommand, LAST_USED_FILE, VersionSpec};
+use anyhow::{Context, Result, anyhow, bail};
+use semver::Version;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::fs;
+
+/// Parse an age such as `30d`: a number followed by one of the units `s`,
+/// `m`, `h`, `d` or `w`
+pub fn parse_age(age: &str) -> Result<Duration> {
+    let age = age.trim();
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (number, unit) = age.split_at(split);
+
+    let number: u64 = number
+        .parse()
+        .with_context(|| anyhow!("Invalid age `{age}', expected e.g. 30d"))?;
+
+    let seconds = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => bail!("Invalid unit in `{age}', expected one of s, m, h, d or w"),
+    };
+
+    let seconds = number
+        .checked_mul(seconds)
+        .with_context(|| anyhow!("Age `{age}' is too large"))?;
+
+    Ok(Duration::from_secs(seconds))
+}
+
+/// A directory of the versions directory
+#[derive(Debug, Clone)]
+pub struct InstalledToolchain {
+    pub path: PathBuf,
+    /// Name of the directory
+    pub name: String,
+    /// The version, unless the name isn't one
+    pub version: Option<Version>,
+    /// Last modification of the directory, i.e. when a target was installed
+    pub installed: SystemTime,
+    /// Last use of any of the targets of the version
+    pub last_used: Option<SystemTime>,
+}
+
+impl InstalledToolchain {
+    pub async fn load(path: PathBuf) -> Result<Self> {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        let installed = fs::metadata(&path)
+            .await
+            .and_then(|metadata| metadata.modified())
+            .with_context(|| anyhow!("Failed to read metadata of `{}'", path.display()))?;
+
+        let last_used = last_used(&path).await;
+
+        Ok(Self {
+            version: name.parse().ok(),
+            path,
+            name,
+            installed,
+            last_used,
+        })
+    }
+}
+
+/// The latest use recorded in the targets of a version directory
+async fn last_used(path: &Path) -> Option<SystemTime> {
+    let mut entries = fs::read_dir(path).await.ok()?;
+    let mut last_used = None;
+
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(content) = fs::read_to_string(entry.path().join(LAST_USED_FILE)).await else {
+            continue;
+        };
+
+        let Ok(seconds) = content.trim().parse() else {
+            continue;
+        };
+
+        last_used = last_used.max(Some(UNIX_EPOCH + Duration::from_secs(seconds)));
+    }
+
+    last_used
+}
+
+/// Selection of the versions kept by `compact clean`
+#[derive(Debug, Clone, Default)]
+pub struct RetentionPolicy {
+    pub keep_latest: Option<usize>,
+    pub keep: Vec<VersionSpec>,
+    pub older_than: Option<Duration>,
+    pub unused_since: Option<Duration>,
+    /// Versions never removed
+    pub protected: Vec<Version>,
+}
+
+impl RetentionPolicy {
+    pub fn new(command: &CleanCommand, protected: Vec<Version>) -> Self {
+        Self {
+            keep_latest: command.keep_latest,
+            keep: command.keep.clone(),
+            older_than: command.older_than,
+            unused_since: command.unused_since,
+            protected,
+        }
+    }
+
+    /// Tell, for each of `toolchains`, whether it is kept
+    pub fn retain(&self, toolchains: &[InstalledToolchain], now: SystemTime) -> Vec<bool> {
+        let latest: BTreeSet<&Version> = match self.keep_latest {
+            Some(count) => {
+                let versions: BTreeSet<_> = toolchains
+                    .iter()
+                    .filter_map(|toolchain| toolchain.version.as_ref())
+                    .collect();
+
+                versions.into_iter().rev().take(count).collect()
+            }
+            None => BTreeSet::new(),
+        };
+
+        let recent = |time: SystemTime, age: Option<Duration>| {
+            age.is_some_and(|age| now.duration_since(time).unwrap_or_default() < age)
+        };
+
+        toolchains
+            .iter()
+            .map(|toolchain| {
+                let kept_version = toolchain.version.as_ref().is_some_and(|version| {
+                    self.protected.contains(version)
+                        || latest.contains(version)
+                        || self.keep.iter().any(|spec| spec.matches_pre(version, true))
+                });
+
+                let last_used = toolchain.last_used.unwrap_or(toolchain.installed);
+
+                kept_version
+                    || recent(toolchain.installed, self.older_than)
+                    || recent(last_used, self.unused_since)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+    fn toolchain(version: &str, installed: u32, last_used: Option<u32>) -> InstalledToolchain {
+        let now = now();
+
+        InstalledToolchain {
+            path: PathBuf::from(version),
+            name: version.to_owned(),
+            version: version.parse().ok(),
+            installed: now - DAY * installed,
+            last_used: last_used.map(|days| now - DAY * days),
+        }
+    }
+
+    fn now() -> SystemTime {
+        UNIX_EPOCH + DAY * 1000
+    }
+
+    fn toolchains() -> Vec<InstalledToolchain> {
+        vec![
+            toolchain("0.27.0", 90, None),
+            toolchain("0.28.0", 60, Some(1)),
+            toolchain("0.29.0", 10, Some(20)),
+            toolchain("0.30.0", 2, None),
+            toolchain("leftover", 100, None),
+        ]
+    }
+
+    #[test]
+    fn parse_ages() {
+        assert_eq!(parse_age("30d").unwrap(), DAY * 30);
+        assert_eq!(parse_age("2w").unwrap(), DAY * 14);
+        assert_eq!(parse_age("12h").unwrap(), Duration::from_secs(12 * 3600));
+        assert_eq!(parse_age("90s").unwrap(), Duration::from_secs(90));
+        assert!(parse_age("30").is_err());
+        assert!(parse_age("d").is_err());
+        assert!(parse_age("3y").is_err());
+        assert!(parse_age("99999999999999999w").is_err());
+        assert!(parse_age("99999999999999999999s").is_err());
+    }
+
+    #[test]
+    fn no_policy() {
+        let policy = RetentionPolicy::default();
+
+        assert_eq!(
+            policy.retain(&toolchains(), now()),
+            [false, false, false, false, false]
+        );
+    }
+
+    #[test]
+    fn keep_latest() {
+        let policy = RetentionPolicy {
+            keep_latest: Some(2),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.retain(&toolchains(), now()),
+            [false, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn keep_spec_and_protected() {
+        let policy = RetentionPolicy {
+            keep: vec!["~0.28".parse().unwrap()],
+            protected: vec![Version::new(0, 27, 0)],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.retain(&toolchains(), now()),
+            [true, true, false, false, false]
+        );
+    }
+
+    #[test]
+    fn older_than() {
+        let policy = RetentionPolicy {
+            older_than: Some(DAY * 30),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.retain(&toolchains(), now()),
+            [false, false, true, true, false]
+        );
+    }
+
+    #[test]
+    fn unused_since() {
+        let policy = RetentionPolicy {
+            unused_since: Some(DAY * 7),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            policy.retain(&toolchains(), now()),
+            [false, true, false, true, false]
+        );
+    }
+}
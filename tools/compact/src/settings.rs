@@ -0,0 +1,106 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{Target, compact_directory::CompactDirectory};
+use anyhow::{Context, Result, anyhow};
+use semver::Version;
+use serde::{Deserialize, Serialize};
+use std::io::ErrorKind;
+use tokio::fs;
+
+/// Persistent settings of a compact directory, stored in `settings.toml`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Settings {
+    /// The toolchain used when no version is requested
+    pub default: Option<DefaultToolchain>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct DefaultToolchain {
+    pub version: Version,
+    pub target: Target,
+}
+
+impl Settings {
+    /// Load the settings, returning the default settings if the file does not
+    /// exist yet.
+    pub async fn load(directory: &CompactDirectory) -> Result<Self> {
+        let path = directory.settings_file();
+
+        let content = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Self::default()),
+            reason => reason.with_context(|| anyhow!("Failed to read settings `{path:?}'"))?,
+        };
+
+        toml::from_str(&content).with_context(|| anyhow!("Invalid settings file `{path:?}'"))
+    }
+
+    /// Save the settings
+    ///
+    /// The file is written next to its destination first and then renamed so
+    /// an interrupted write never leaves a truncated file behind.
+    pub async fn save(&self, directory: &CompactDirectory) -> Result<()> {
+        let path = directory.settings_file();
+        let partial = path.with_extension("toml.partial");
+
+        let content = toml::to_string(self).context("Failed to serialize settings")?;
+
+        fs::write(&partial, content)
+            .await
+            .with_context(|| anyhow!("Failed to write settings `{partial:?}'"))?;
+        fs::rename(&partial, &path)
+            .await
+            .with_context(|| anyhow!("Failed to write settings `{path:?}'"))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn missing_file_is_default() {
+        let dir = tempfile::tempdir().unwrap();
+        let directory: CompactDirectory = dir.path().to_str().unwrap().parse().unwrap();
+
+        let settings = Settings::load(&directory).await.unwrap();
+
+        assert_eq!(settings, Settings::default());
+    }
+
+    #[tokio::test]
+    async fn save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let directory: CompactDirectory = dir.path().to_str().unwrap().parse().unwrap();
+
+        let settings = Settings {
+            default: Some(DefaultToolchain {
+                version: Version::new(0, 29, 0),
+                target: Target::x86_64UnknownLinuxMusl,
+            }),
+        };
+
+        settings.save(&directory).await.unwrap();
+
+        assert_eq!(Settings::load(&directory).await.unwrap(), settings);
+        assert_eq!(
+            std::fs::read_to_string(directory.settings_file()).unwrap(),
+            "[default]\nversion = \"0.29.0\"\ntarget = \"x86_64-unknown-linux-musl\"\n"
+        );
+    }
+}
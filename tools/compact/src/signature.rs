@@ -0,0 +1,222 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Minisign signatures of release assets
+//!
+//! Every release asset may be published with a detached minisign signature,
+//! `<asset>.minisig`. Signatures are checked against the public key embedded
+//! in the `compact` binary at build time (from `COMPACT_RELEASE_PUBLIC_KEY`),
+//! or against the key given with `--public-key` / `COMPACT_PUBLIC_KEY` for
+//! mirrors signing with their own key. Without any key, signatures are not
+//! checked and installs rely on the checksums alone.
+
+use anyhow::{Context, Result, anyhow, bail};
+use minisign_verify::{PublicKey, Signature};
+use std::{io::Read as _, path::Path};
+
+/// Public key the official release assets are signed with
+///
+/// Set by the release builds; without it, and without `--public-key`,
+/// signatures are not checked.
+pub const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("COMPACT_RELEASE_PUBLIC_KEY");
+
+/// Extension of the signature files
+pub const SIGNATURE_EXTENSION: &str = ".minisig";
+
+/// Returns true if the asset `name` is a signature file
+pub fn is_signature_file(name: &str) -> bool {
+    name.ends_with(SIGNATURE_EXTENSION)
+}
+
+/// Parse a public key given as its base64 encoding, as the content of a
+/// `minisign.pub` file, or as the path to such a file.
+pub fn parse_public_key(key: &str) -> Result<PublicKey> {
+    let key = key.trim();
+
+    let content = if Path::new(key).is_file() {
+        std::fs::read_to_string(key)
+            .with_context(|| anyhow!("Failed to read public key file `{key}'"))?
+    } else {
+        key.to_owned()
+    };
+
+    let content = content.trim();
+
+    let public_key = if content.lines().count() > 1 {
+        PublicKey::decode(content)
+    } else {
+        PublicKey::from_base64(content)
+    };
+
+    public_key.map_err(|error| anyhow!("Invalid public key: {error}"))
+}
+
+/// Verify the file at `path` against the detached `signature`
+///
+/// Only pre-hashed signatures (the default of minisign) are accepted. The
+/// trusted comment of the signature is returned on success.
+pub async fn verify_file(
+    path: impl AsRef<Path>,
+    signature: &str,
+    public_key: &PublicKey,
+) -> Result<String> {
+    let path = path.as_ref().to_path_buf();
+    let signature =
+        Signature::decode(signature).map_err(|error| anyhow!("Invalid signature file: {error}"))?;
+    let public_key = public_key.clone();
+
+    tokio::task::spawn_blocking(move || {
+        let mut file = std::fs::File::open(&path)
+            .with_context(|| anyhow!("Failed to open file `{}'", path.display()))?;
+
+        let mut verifier = public_key
+            .verify_stream(&signature)
+            .map_err(|error| anyhow!("Signature verification failed: {error}"))?;
+
+        let mut buffer = vec![0; 64 * 1024];
+        loop {
+            let read = file
+                .read(&mut buffer)
+                .with_context(|| anyhow!("Failed to read file `{}'", path.display()))?;
+            if read == 0 {
+                break;
+            }
+            verifier.update(&buffer[..read]);
+        }
+
+        verifier
+            .finalize()
+            .map_err(|error| anyhow!("Signature verification failed: {error}"))?;
+
+        Ok(signature.trusted_comment().to_owned())
+    })
+    .await
+    .context("Failed to verify the signature")?
+}
+
+/// Check that the trusted comment of a signature names the file `name`
+///
+/// The trusted comment is covered by the signature: this prevents a validly
+/// signed asset from being served in place of another one.
+pub fn check_signed_file(trusted_comment: &str, name: &str) -> Result<()> {
+    let file = trusted_comment
+        .split('\t')
+        .find_map(|field| field.strip_prefix("file:"));
+
+    match file {
+        Some(file) if file == name => Ok(()),
+        Some(file) => bail!("The signature is for `{file}', not `{name}'"),
+        None => bail!("The signature doesn't name the signed file"),
+    }
+}
+
+#[cfg(test)]
+#[path = "../tests/common/signing.rs"]
+mod test_signing;
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    pub(crate) use super::test_signing::{public_key, sign};
+
+    const OTHER_PUBLIC_KEY: &str = "RWQBAgMEBQYHCP0XJDhaoMdbZPt4zWAvodmR/ev3axPFjtcC6sg16fYY";
+    pub(crate) const CONTENT: &[u8] = b"compact test artifact\n";
+
+    fn artifact(content: &[u8]) -> (tempfile::TempDir, std::path::PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("artifact.zip");
+        std::fs::write(&path, content).unwrap();
+        (dir, path)
+    }
+
+    #[test]
+    fn parse_public_keys() {
+        assert!(parse_public_key(&public_key()).is_ok());
+        assert!(
+            parse_public_key(&format!(
+                "untrusted comment: minisign public key\n{}\n",
+                public_key()
+            ))
+            .is_ok()
+        );
+        assert!(parse_public_key("not a key").is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("minisign.pub");
+        std::fs::write(
+            &path,
+            format!("untrusted comment: minisign public key\n{}\n", public_key()),
+        )
+        .unwrap();
+        assert!(parse_public_key(path.to_str().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn signature_files() {
+        assert!(is_signature_file(
+            "compactc_v0.29.0_aarch64-darwin.zip.minisig"
+        ));
+        assert!(!is_signature_file("compactc_v0.29.0_aarch64-darwin.zip"));
+    }
+
+    #[tokio::test]
+    async fn valid_signature() {
+        let (_dir, path) = artifact(CONTENT);
+        let key = parse_public_key(&public_key()).unwrap();
+
+        let trusted_comment = verify_file(&path, &sign(CONTENT, "artifact.zip"), &key)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            trusted_comment,
+            "timestamp:1760000000\tfile:artifact.zip\thashed"
+        );
+    }
+
+    #[test]
+    fn signed_file_names() {
+        let trusted_comment = "timestamp:1760000000\tfile:artifact.zip\thashed";
+
+        assert!(check_signed_file(trusted_comment, "artifact.zip").is_ok());
+        assert!(check_signed_file(trusted_comment, "other.zip").is_err());
+        assert!(check_signed_file("timestamp:1760000000", "artifact.zip").is_err());
+    }
+
+    #[tokio::test]
+    async fn tampered_file() {
+        let (_dir, path) = artifact(b"compact test artifact, tampered\n");
+        let key = parse_public_key(&public_key()).unwrap();
+
+        assert!(
+            verify_file(&path, &sign(CONTENT, "artifact.zip"), &key)
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn wrong_public_key() {
+        let (_dir, path) = artifact(CONTENT);
+        let key = parse_public_key(OTHER_PUBLIC_KEY).unwrap();
+
+        assert!(
+            verify_file(&path, &sign(CONTENT, "artifact.zip"), &key)
+                .await
+                .is_err()
+        );
+    }
+}
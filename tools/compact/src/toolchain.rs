@@ -0,0 +1,263 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use crate::{CommandLineArguments, Compiler, VersionSpec, utils};
+use anyhow::{Context, Result, anyhow};
+use semver::Version;
+use serde::Deserialize;
+use std::{
+    ffi::OsString,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Name of the file pinning a project to a toolchain version
+///
+/// The file is looked up in the current directory and then in every parent
+/// directory until one is found.
+pub const TOOLCHAIN_FILE: &str = "compact-toolchain.toml";
+
+/// Environment variable overriding the toolchain version for a single run
+pub const TOOLCHAIN_ENV: &str = "COMPACT_TOOLCHAIN";
+
+/// Content of a `compact-toolchain.toml` file
+///
+/// ```toml
+/// [toolchain]
+/// version = "0.29.0"
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+struct ToolchainFile {
+    toolchain: ToolchainSection,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ToolchainSection {
+    version: String,
+}
+
+/// Where a toolchain override was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolchainSource {
+    /// The `COMPACT_TOOLCHAIN` environment variable
+    Environment,
+    /// A `compact-toolchain.toml` file
+    File(PathBuf),
+}
+
+/// A toolchain version requested for the current project, taking precedence
+/// over the global default compiler.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ToolchainOverride {
+    pub spec: VersionSpec,
+    pub source: ToolchainSource,
+}
+
+impl ToolchainOverride {
+    /// Look for a toolchain override, the environment variable first and then
+    /// a toolchain file in `cwd` or any of its parents.
+    pub async fn find(cwd: &Path) -> Result<Option<Self>> {
+        Self::find_with(cwd, std::env::var_os(TOOLCHAIN_ENV)).await
+    }
+
+    async fn find_with(cwd: &Path, env: Option<OsString>) -> Result<Option<Self>> {
+        if let Some(value) = env.filter(|value| !value.is_empty()) {
+            let value = value.to_string_lossy();
+            let spec = value
+                .trim()
+                .parse()
+                .with_context(|| anyhow!("Invalid version `{value}' in {TOOLCHAIN_ENV}"))?;
+
+            return Ok(Some(Self {
+                spec,
+                source: ToolchainSource::Environment,
+            }));
+        }
+
+        let Some(path) = find_toolchain_file(cwd) else {
+            return Ok(None);
+        };
+
+        let spec = read_toolchain_file(&path).await?;
+
+        Ok(Some(Self {
+            spec,
+            source: ToolchainSource::File(path),
+        }))
+    }
+
+    /// Select the newest installed compiler matching the requested version
+    pub async fn resolve(&self, cfg: &CommandLineArguments) -> Result<Compiler> {
+        let version = find_installed(cfg, &self.spec).await?.ok_or_else(|| {
+            anyhow!(
+                "Toolchain {spec} requested by {source} is not installed, try `compact update {spec}'",
+                spec = self.spec,
+                source = self.source,
+            )
+        })?;
+
+        Compiler::open(cfg, version, cfg.target).await
+    }
+}
+
+impl fmt::Display for ToolchainSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToolchainSource::Environment => TOOLCHAIN_ENV.fmt(f),
+            ToolchainSource::File(path) => write!(f, "`{}'", path.display()),
+        }
+    }
+}
+
+/// Select the newest installed version matching `spec`
+pub async fn find_installed(
+    cfg: &CommandLineArguments,
+    spec: &VersionSpec,
+) -> Result<Option<Version>> {
+    let installed = utils::installed_versions(cfg).await?;
+
+    Ok(installed
+        .into_iter()
+        .rev()
+        .find(|version| spec.matches(version)))
+}
+
+/// Find the closest toolchain file, starting from `start` and walking up
+pub fn find_toolchain_file(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(TOOLCHAIN_FILE))
+        .find(|path| path.is_file())
+}
+
+/// Read the version requested by a toolchain file
+pub async fn read_toolchain_file(path: &Path) -> Result<VersionSpec> {
+    let content = tokio::fs::read_to_string(path)
+        .await
+        .with_context(|| anyhow!("Failed to read toolchain file `{}'", path.display()))?;
+
+    parse_toolchain_file(&content)
+        .with_context(|| anyhow!("Invalid toolchain file `{}'", path.display()))
+}
+
+fn parse_toolchain_file(content: &str) -> Result<VersionSpec> {
+    let file: ToolchainFile = toml::from_str(content)?;
+    let version = file.toolchain.version;
+
+    version
+        .trim()
+        .parse()
+        .with_context(|| anyhow!("Invalid version `{version}'"))
+}
+
+/// Return the compiler to use from the current directory
+///
+/// This is the compiler requested by `COMPACT_TOOLCHAIN` or by the closest
+/// `compact-toolchain.toml` when there is one, the default compiler otherwise.
+pub async fn active_compiler(cfg: &CommandLineArguments) -> Result<Option<Compiler>> {
+    let cwd = std::env::current_dir().context("Failed to read the current directory")?;
+
+    match ToolchainOverride::find(&cwd).await? {
+        Some(toolchain) => toolchain.resolve(cfg).await.map(Some),
+        None => utils::get_current_compiler(cfg).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_exact_version() {
+        let spec = parse_toolchain_file("[toolchain]\nversion = \"0.29.0\"\n").unwrap();
+        assert_eq!(spec, VersionSpec::Exact(Version::new(0, 29, 0)));
+    }
+
+    #[test]
+    fn parse_partial_version() {
+        let spec = parse_toolchain_file("[toolchain]\nversion = \"0.29\"\n").unwrap();
+        assert_eq!(
+            spec,
+            VersionSpec::Partial {
+                major: 0,
+                minor: 29
+            }
+        );
+    }
+
+    #[test]
+    fn parse_invalid_file() {
+        assert!(parse_toolchain_file("version = \"0.29.0\"").is_err());
+        assert!(parse_toolchain_file("[toolchain]\nversion = \"bob\"").is_err());
+    }
+
+    #[tokio::test]
+    async fn file_found_in_parent_directory() {
+        let root = tempfile::tempdir().unwrap();
+        let nested = root.path().join("contracts").join("src");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            root.path().join(TOOLCHAIN_FILE),
+            "[toolchain]\nversion = \"0.28.0\"\n",
+        )
+        .unwrap();
+
+        let toolchain = ToolchainOverride::find_with(&nested, None)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(toolchain.spec, VersionSpec::Exact(Version::new(0, 28, 0)));
+        assert_eq!(
+            toolchain.source,
+            ToolchainSource::File(root.path().join(TOOLCHAIN_FILE))
+        );
+    }
+
+    #[tokio::test]
+    async fn environment_takes_precedence_over_file() {
+        let root = tempfile::tempdir().unwrap();
+        std::fs::write(
+            root.path().join(TOOLCHAIN_FILE),
+            "[toolchain]\nversion = \"0.28.0\"\n",
+        )
+        .unwrap();
+
+        let toolchain = ToolchainOverride::find_with(root.path(), Some("0.29".into()))
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            toolchain.spec,
+            VersionSpec::Partial {
+                major: 0,
+                minor: 29
+            }
+        );
+        assert_eq!(toolchain.source, ToolchainSource::Environment);
+    }
+
+    #[tokio::test]
+    async fn no_override() {
+        let root = tempfile::tempdir().unwrap();
+
+        let toolchain = ToolchainOverride::find_with(root.path(), Some("".into()))
+            .await
+            .unwrap();
+
+        assert!(toolchain.is_none());
+    }
+}
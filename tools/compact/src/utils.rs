@@ -16,30 +16,60 @@
 use std::{
     io::ErrorKind,
     path::{Path, PathBuf},
+    process::{ExitStatus, Output},
+    time::Instant,
 };
 
-use crate::{CommandLineArguments, Target, compiler::Compiler};
+use crate::{
+    CommandLineArguments, Target,
+    compiler::Compiler,
+    lock::{Lock, SETTINGS_LOCK},
+    proxy,
+    settings::{DefaultToolchain, Settings},
+};
 use anyhow::{Context, Result, anyhow, ensure};
 use semver::Version;
 use tokio::fs;
 
-pub async fn read_parent_name_from_link(path: &PathBuf) -> Option<(PathBuf, String)> {
-    fs::read_link(&path).await.ok().and_then(|link| {
-        link.parent().and_then(|parent| {
-            parent.file_name().and_then(|l| l.to_str()).and_then(|l| {
-                if l.contains('v') {
-                    Some((link.clone(), l.to_string()))
-                } else {
-                    parent.parent().and_then(|parent| {
-                        parent
-                            .file_name()
-                            .and_then(|l| l.to_str())
-                            .map(|l| (link.clone(), l.to_string()))
-                    })
-                }
-            })
-        })
-    })
+/// Trace `command` before it runs, returns when it started
+pub fn trace_spawn(command: &std::process::Command) -> Instant {
+    tracing::debug!(
+        program = %command.get_program().to_string_lossy(),
+        args = ?command.get_args().collect::<Vec<_>>(),
+        "running command"
+    );
+
+    Instant::now()
+}
+
+/// Trace the exit `status` of `command`, running since `start`
+pub fn trace_exit(
+    command: &std::process::Command,
+    start: Instant,
+    status: Result<ExitStatus, &std::io::Error>,
+) {
+    let program = command.get_program().to_string_lossy();
+    let elapsed = start.elapsed();
+
+    match status {
+        Ok(status) => tracing::debug!(%program, %status, ?elapsed, "command exited"),
+        Err(error) => tracing::debug!(%program, %error, ?elapsed, "command failed"),
+    }
+}
+
+/// Run `command` to completion, tracing its arguments, exit status and
+/// duration
+pub async fn command_output(command: &mut tokio::process::Command) -> std::io::Result<Output> {
+    let start = trace_spawn(command.as_std());
+    let output = command.output().await;
+
+    trace_exit(
+        command.as_std(),
+        start,
+        output.as_ref().map(|output| output.status),
+    );
+
+    output
 }
 
 pub async fn remove_file_if_exists(path: &PathBuf) -> Result<()> {
@@ -52,6 +82,32 @@ pub async fn remove_file_if_exists(path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
+/// Total size of the files under `path`
+pub async fn disk_usage(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    let mut pending = vec![path.to_path_buf()];
+
+    while let Some(path) = pending.pop() {
+        let metadata = fs::symlink_metadata(&path)
+            .await
+            .with_context(|| anyhow!("Failed to read metadata of `{}'", path.display()))?;
+
+        if metadata.is_dir() {
+            let mut entries = fs::read_dir(&path)
+                .await
+                .with_context(|| anyhow!("Failed to read directory: `{path:?}'"))?;
+
+            while let Some(entry) = entries.next_entry().await? {
+                pending.push(entry.path());
+            }
+        } else {
+            size += metadata.len();
+        }
+    }
+
+    Ok(size)
+}
+
 async fn initialise_directory(path: impl AsRef<Path>) -> Result<()> {
     let path = path.as_ref();
 
@@ -74,65 +130,23 @@ pub async fn initialise_directories(cfg: &CommandLineArguments) -> Result<()> {
     Ok(())
 }
 
-#[cfg(unix)]
+/// Make `compiler` the default toolchain and install the proxies selecting
+/// it in the `bin` directory
 pub async fn set_current_compiler(
     cfg: &CommandLineArguments,
     compiler: &Compiler,
 ) -> Result<Compiler> {
-    // set compactc
-    let source = compiler.path_compactc().to_path_buf();
-    let target = cfg.directory.bin_dir().join("compactc");
-
-    if target.is_symlink() {
-        fs::remove_file(&target)
-            .await
-            .with_context(|| anyhow!("Failed to remove previous symlink {target:?}"))?;
-    }
-
-    fs::symlink(&source, &target).await.with_context(|| {
-        anyhow!(
-            "Failed to create symlink from {target:?} {arrow} {source:?}",
-            arrow = console::Emoji::new("→", "to")
-        )
-    })?;
+    let _lock = Lock::directory(cfg, SETTINGS_LOCK).await?;
 
-    // set format-compact
-    let source = compiler.path_format_compact();
-    let target = cfg.directory.bin_dir().join("format-compact");
-
-    if target.is_symlink() {
-        fs::remove_file(&target)
-            .await
-            .with_context(|| anyhow!("Failed to remove previous symlink {target:?}"))?;
-    }
-
-    if source.exists() {
-        fs::symlink(&source, &target).await.with_context(|| {
-            anyhow!(
-                "Failed to create symlink from {target:?} {arrow} {source:?}",
-                arrow = console::Emoji::new("→", "to")
-            )
-        })?;
-    }
-
-    // set fixup-compact
-    let source = compiler.path_fixup_compact();
-    let target = cfg.directory.bin_dir().join("fixup-compact");
-
-    if target.is_symlink() {
-        fs::remove_file(&target)
-            .await
-            .with_context(|| anyhow!("Failed to remove previous symlink {target:?}"))?;
-    }
+    let mut settings = Settings::load(&cfg.directory).await?;
+    settings.default = Some(DefaultToolchain {
+        version: compiler.version().clone(),
+        target: compiler.target(),
+    });
+    settings.save(&cfg.directory).await?;
+    tracing::debug!(version = %compiler.version(), target = %compiler.target(), "default toolchain set");
 
-    if source.exists() {
-        fs::symlink(&source, &target).await.with_context(|| {
-            anyhow!(
-                "Failed to create symlink from {target:?} {arrow} {source:?}",
-                arrow = console::Emoji::new("→", "to")
-            )
-        })?;
-    }
+    proxy::install_proxies(cfg).await?;
 
     let new = get_current_compiler(cfg)
         .await?
@@ -147,9 +161,51 @@ pub async fn set_current_compiler(
     Ok(new)
 }
 
+/// Clear the default toolchain and remove the proxies
+pub async fn unset_current_compiler(cfg: &CommandLineArguments) -> Result<()> {
+    let _lock = Lock::directory(cfg, SETTINGS_LOCK).await?;
+
+    let mut settings = Settings::load(&cfg.directory).await?;
+
+    if let Some(default) = settings.default.take() {
+        settings.save(&cfg.directory).await?;
+        tracing::debug!(version = %default.version, "default toolchain unset");
+    }
+
+    proxy::remove_proxies(cfg).await
+}
+
 pub async fn get_current_compiler(cfg: &CommandLineArguments) -> Result<Option<Compiler>> {
+    match get_default_toolchain(cfg).await? {
+        Some((version, target)) => Compiler::open(cfg, version, target).await.map(Some),
+        None => Ok(None),
+    }
+}
+
+/// Version and target recorded as the default toolchain, whether or not it
+/// is still installed
+pub async fn get_default_toolchain(
+    cfg: &CommandLineArguments,
+) -> Result<Option<(Version, Target)>> {
+    let settings = Settings::load(&cfg.directory).await?;
+
+    if let Some(default) = settings.default {
+        return Ok(Some((default.version, default.target)));
+    }
+
+    get_legacy_default(cfg).await
+}
+
+/// Older versions of compact recorded the default compiler as a symbolic link
+/// `bin/compactc` to the compiler binary, it is still honoured until the next
+/// time a default is set.
+async fn get_legacy_default(cfg: &CommandLineArguments) -> Result<Option<(Version, Target)>> {
     let bin = cfg.directory.bin_dir().join("compactc");
 
+    if !bin.is_symlink() {
+        return Ok(None);
+    }
+
     let file = match fs::read_link(&bin).await {
         Ok(file) => {
             ensure!(file.is_file(), "Expecting a file: `{file:?}'");
@@ -186,5 +242,45 @@ pub async fn get_current_compiler(cfg: &CommandLineArguments) -> Result<Option<C
         .parse()
         .with_context(|| anyhow!("Couldn't parse the version parent directory ({parent:?})"))?;
 
-    Compiler::open(cfg, version, target).await.map(Some)
+    Ok(Some((version, target)))
+}
+
+/// List the versions installed for the configured target, oldest first
+pub async fn installed_versions(cfg: &CommandLineArguments) -> Result<Vec<Version>> {
+    installed_versions_for(cfg, cfg.target).await
+}
+
+/// List the versions installed for `target`, oldest first
+pub async fn installed_versions_for(
+    cfg: &CommandLineArguments,
+    target: Target,
+) -> Result<Vec<Version>> {
+    let dir = cfg.directory.versions_dir();
+
+    let mut entries = match fs::read_dir(&dir).await {
+        Ok(entries) => entries,
+        Err(error) if error.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        reason => reason.with_context(|| anyhow!("Failed to read directory: `{dir:?}'"))?,
+    };
+
+    let mut versions = Vec::new();
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .context("Failed to load next version entry")?
+    {
+        let Ok(version) = entry.file_name().to_string_lossy().parse::<Version>() else {
+            continue;
+        };
+
+        let compactc = entry.path().join(target.to_string()).join("compactc");
+
+        if compactc.is_file() {
+            versions.push(version);
+        }
+    }
+
+    versions.sort();
+
+    Ok(versions)
 }
@@ -18,6 +18,11 @@ use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::{fs, io};
 
+mod signing;
+
+#[allow(unused_imports)]
+pub use signing::{public_key, sign};
+
 #[allow(dead_code)]
 pub const COMPACT_VERSION: &str = "0.5.2";
 
@@ -335,3 +340,94 @@ pub fn download_to_temp(version: &str, home_dir: &str, receipt_dir: &str) {
 
     assert_eq!(exit_code, Some(0));
 }
+
+// install a stand-in toolchain whose programs print their name, version and
+// arguments
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn install_fake_compiler(directory: &Path, version: &str) -> PathBuf {
+    use std::os::unix::fs::PermissionsExt;
+
+    let target_dir = directory.join("versions").join(version).join(get_version());
+
+    fs::create_dir_all(&target_dir).unwrap();
+
+    for program in ["compactc", "format-compact", "fixup-compact"] {
+        let path = target_dir.join(program);
+        fs::write(
+            &path,
+            format!("#!/bin/sh\necho \"{program} {version} $@\"\n"),
+        )
+        .unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    target_dir
+}
+
+/// Environment verifying the signatures of the fake registries
+#[allow(dead_code)]
+pub fn public_key_env() -> HashMap<String, String> {
+    HashMap::from([("COMPACT_PUBLIC_KEY".to_string(), public_key())])
+}
+
+/// Create a local release registry serving a fake toolchain for `version`,
+/// with the digest of the archive in the index and its signature. Calling it
+/// again on the same directory adds the release to the index.
+#[cfg(unix)]
+#[allow(dead_code)]
+pub fn create_fake_registry(directory: &Path, version: &str) -> PathBuf {
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    let tag = format!("compactc-v{version}");
+    let name = format!("compactc_v{version}_{}.zip", get_version());
+
+    fs::create_dir_all(directory.join(&tag)).unwrap();
+
+    let mut archive = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+    for program in ["compactc", "format-compact", "fixup-compact"] {
+        archive
+            .start_file(
+                program,
+                SimpleFileOptions::default().unix_permissions(0o755),
+            )
+            .unwrap();
+        write!(archive, "#!/bin/sh\necho \"{program} {version} $@\"\n").unwrap();
+    }
+    let archive = archive.finish().unwrap().into_inner();
+
+    fs::write(directory.join(&tag).join(&name), &archive).unwrap();
+    fs::write(
+        directory.join(&tag).join(format!("{name}.minisig")),
+        sign(&archive, &name),
+    )
+    .unwrap();
+
+    let index_path = directory.join("index.json");
+    let mut index: serde_json::Value = fs::read_to_string(&index_path)
+        .map(|index| serde_json::from_str(&index).unwrap())
+        .unwrap_or_else(|_| serde_json::json!({ "releases": [] }));
+
+    index["releases"]
+        .as_array_mut()
+        .unwrap()
+        .push(serde_json::json!({
+            "tag_name": tag,
+            "body": format!("Release notes of {version}."),
+            "assets": [
+                {
+                    "name": name,
+                    "size": archive.len(),
+                    "digest": format!("sha256:{}", hex::encode(Sha256::digest(&archive))),
+                },
+                {
+                    "name": format!("{name}.minisig"),
+                },
+            ]
+        }));
+    fs::write(&index_path, index.to_string()).unwrap();
+
+    directory.to_path_buf()
+}
@@ -0,0 +1,51 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+//! Throw-away minisign key pair signing the test artifacts
+//!
+//! Shared by the unit tests of the `signature` module and the fake registries
+//! of the integration tests.
+
+#![allow(dead_code)]
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use blake2::{Blake2b512, Digest as _};
+use ed25519_dalek::{Signer as _, SigningKey};
+
+const SECRET_KEY: [u8; 32] = [42; 32];
+const KEY_ID: [u8; 8] = [1, 2, 3, 4, 5, 6, 7, 8];
+
+/// Base64 public key of the test key pair
+pub fn public_key() -> String {
+    let key = SigningKey::from_bytes(&SECRET_KEY).verifying_key();
+
+    STANDARD.encode([b"Ed".as_slice(), &KEY_ID, key.as_bytes()].concat())
+}
+
+/// Sign `content` as minisign does for a file named `file`
+pub fn sign(content: &[u8], file: &str) -> String {
+    let key = SigningKey::from_bytes(&SECRET_KEY);
+    let signature = key.sign(&Blake2b512::digest(content)).to_bytes();
+    let trusted_comment = format!("timestamp:1760000000\tfile:{file}\thashed");
+    let global_signature = key
+        .sign(&[signature.as_slice(), trusted_comment.as_bytes()].concat())
+        .to_bytes();
+
+    format!(
+        "untrusted comment: signature from minisign secret key\n{}\ntrusted comment: {trusted_comment}\n{}\n",
+        STANDARD.encode([b"ED".as_slice(), &KEY_ID, &signature].concat()),
+        STANDARD.encode(global_signature),
+    )
+}
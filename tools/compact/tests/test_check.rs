@@ -13,7 +13,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crate::common::{COMPACT_VERSION, LATEST_COMPACTC_VERSION, run_command};
+use crate::common::{COMPACT_VERSION, LATEST_COMPACTC_VERSION, get_version, run_command};
 use std::env;
 
 mod common;
@@ -26,7 +26,7 @@ fn test_compact_check_no_param() {
         Some("./output/check/std_default.txt"),
         None,
         &[("[LATEST_COMPACTC_VERSION]", LATEST_COMPACTC_VERSION)],
-        None,
+        Some(11),
     );
 }
 
@@ -89,3 +89,115 @@ fn test_compact_check_param_v() {
         Some(0),
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn test_compact_check_exit_codes() {
+    use crate::common::create_fake_registry;
+    use std::collections::HashMap;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let directory = temp_dir.path().display().to_string();
+    let registry_dir = tempfile::tempdir().unwrap();
+    create_fake_registry(registry_dir.path(), "0.98.0");
+    create_fake_registry(registry_dir.path(), "0.99.0");
+    create_fake_registry(registry_dir.path(), "1.0.0-rc.1");
+    let registry = registry_dir.path().display().to_string();
+
+    let compact = |args: &[&str], pin: Option<&str>, stdout: &str, version: &str, code: i32| {
+        let args = [&["--directory", &directory, "--registry", &registry], args].concat();
+        let mut env = HashMap::new();
+        if let Some(pin) = pin {
+            env.insert("COMPACT_TOOLCHAIN".to_string(), pin.to_string());
+        }
+
+        run_command(
+            &args,
+            Some(env),
+            Some(stdout),
+            None,
+            &[
+                ("[LATEST_COMPACTC_VERSION]", version),
+                ("[SYSTEM_VERSION]", get_version()),
+            ],
+            Some(code),
+        );
+    };
+
+    compact(
+        &["check"],
+        None,
+        "./output/check/std_default.txt",
+        "0.99.0",
+        11,
+    );
+
+    compact(
+        &["update", "0.98.0"],
+        None,
+        "./output/update/std_default.txt",
+        "0.98.0",
+        0,
+    );
+    compact(
+        &["check"],
+        None,
+        "./output/check/std_update_available.txt",
+        "0.99.0",
+        0,
+    );
+    compact(
+        &["check", "--fail-on-outdated"],
+        None,
+        "./output/check/std_update_available.txt",
+        "0.99.0",
+        10,
+    );
+
+    compact(
+        &["check"],
+        Some("0.99"),
+        "./output/check/std_pin_missing.txt",
+        "0.99.0",
+        11,
+    );
+
+    compact(
+        &["update", "--no-set-default", "0.99.0"],
+        None,
+        "./output/update/std_no_default.txt",
+        "0.99.0",
+        0,
+    );
+    compact(
+        &["check"],
+        Some("0.99"),
+        "./output/check/std_pin_mismatch.txt",
+        "0.99.0",
+        12,
+    );
+    compact(
+        &["check"],
+        Some("0.98"),
+        "./output/check/std_pin_up_to_date.txt",
+        "0.98.0",
+        0,
+    );
+
+    // the pre-release is not an update of the newest stable version
+    run_command(
+        &["--directory", &directory, "--quiet", "default", "0.99"],
+        None,
+        None,
+        None,
+        &[],
+        Some(0),
+    );
+    compact(
+        &["check", "--fail-on-outdated"],
+        None,
+        "./output/check/std_up_to_date.txt",
+        "0.99.0",
+        0,
+    );
+}
@@ -113,3 +113,84 @@ fn test_compact_clean_param_v() {
         Some(0),
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn test_compact_clean_retention_policies() {
+    use crate::common::install_fake_compiler;
+    use std::collections::HashMap;
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let directory = format!("{}", temp_path.display());
+
+    for version in ["0.97.0", "0.98.0", "0.99.0"] {
+        install_fake_compiler(temp_path, version);
+    }
+
+    run_command(
+        &[
+            "--directory",
+            &directory,
+            "clean",
+            "--dry-run",
+            "--keep-latest",
+            "1",
+        ],
+        None,
+        Some("./output/clean/std_dry_run.txt"),
+        None,
+        &[],
+        Some(0),
+    );
+
+    // the version pinned by the project is kept
+    run_command(
+        &["--directory", &directory, "clean", "--keep", "0.97"],
+        Some(HashMap::from([(
+            "COMPACT_TOOLCHAIN".to_string(),
+            "0.98.0".to_string(),
+        )])),
+        Some("./output/clean/std_keep_pinned.txt"),
+        None,
+        &[],
+        Some(0),
+    );
+
+    assert!(temp_path.join("versions/0.97.0").is_dir());
+    assert!(temp_path.join("versions/0.98.0").is_dir());
+    assert!(!temp_path.join("versions/0.99.0").exists());
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_compact_clean_cache_removes_legacy_cache() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let home = tempfile::tempdir().unwrap();
+    let legacy_cache = home.path().join(".cache/compactc/github_cache.json");
+
+    std::fs::create_dir_all(legacy_cache.parent().unwrap()).unwrap();
+    std::fs::write(&legacy_cache, "{}").unwrap();
+
+    let clean = |args: &[&str]| {
+        let output = std::process::Command::new("../../target/debug/compact")
+            .args(["--directory", &format!("{}", temp_dir.path().display())])
+            .args(["clean", "--cache"])
+            .args(args)
+            .env("HOME", home.path())
+            .env_remove("XDG_CACHE_HOME")
+            .output()
+            .unwrap();
+
+        assert_eq!(output.status.code(), Some(0), "{output:?}");
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    let stdout = clean(&["--dry-run"]);
+    assert!(stdout.contains("github_cache.json"), "{stdout}");
+    assert!(legacy_cache.is_file());
+
+    let stdout = clean(&[]);
+    assert!(stdout.contains("github_cache.json"), "{stdout}");
+    assert!(!legacy_cache.exists());
+}
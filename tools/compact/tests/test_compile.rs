@@ -14,6 +14,7 @@
 // limitations under the License.
 
 use crate::common::{LATEST_COMPACTC_VERSION, get_version, run_command};
+use std::collections::HashMap;
 use std::env;
 
 mod common;
@@ -74,6 +75,30 @@ fn test_compact_compile_no_compiler_installed_pass_directory() {
     );
 }
 
+#[test]
+fn test_compact_compile_toolchain_env_not_installed() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let compiler = "0.21";
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "compile",
+            "--version",
+        ],
+        Some(HashMap::from([(
+            "COMPACT_TOOLCHAIN".to_string(),
+            compiler.to_string(),
+        )])),
+        None,
+        Some("./output/compile/err_toolchain_not_installed.txt"),
+        &[("[COMPILER]", compiler)],
+        Some(1),
+    );
+}
+
 #[test]
 fn test_compile_contract_no_compiler_installed() {
     let temp_output = tempfile::tempdir().unwrap();
@@ -0,0 +1,68 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use crate::common::{get_version, install_fake_compiler, run_command};
+
+mod common;
+
+#[test]
+fn test_compact_default() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let directory = format!("{}", temp_path.display());
+
+    install_fake_compiler(temp_path, "0.98.0");
+    install_fake_compiler(temp_path, "0.99.0");
+
+    let replacements = [("[SYSTEM_VERSION]", get_version())];
+
+    run_command(
+        &["--directory", &directory, "default"],
+        None,
+        Some("./output/default/std_none.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    run_command(
+        &["--directory", &directory, "use", "0.98"],
+        None,
+        Some("./output/default/std_default.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    run_command(
+        &["--output", "json", "--directory", &directory, "default"],
+        None,
+        Some("./output/default/std_json.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    run_command(
+        &["--directory", &directory, "default", "0.97"],
+        None,
+        None,
+        Some("./output/default/err_not_installed.txt"),
+        &replacements,
+        Some(1),
+    );
+}
@@ -0,0 +1,142 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use crate::common::{get_version, install_fake_compiler, run_command};
+use std::collections::HashMap;
+
+mod common;
+
+#[test]
+fn test_compact_doctor() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let directory = format!("{}", temp_path.display());
+
+    let toolchain = install_fake_compiler(temp_path, "0.99.0");
+
+    let replacements = [
+        ("[SYSTEM_VERSION]", get_version()),
+        ("[DIRECTORY]", directory.as_str()),
+    ];
+    let env = || {
+        let path = std::env::var("PATH").unwrap_or_default();
+
+        Some(HashMap::from([(
+            "PATH".to_string(),
+            format!("{directory}/bin:{path}"),
+        )]))
+    };
+
+    run_command(
+        &["--directory", &directory, "update", "0.99.0"],
+        None,
+        Some("./output/uninstall/std_update.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    run_command(
+        &["--directory", &directory, "doctor"],
+        env(),
+        Some("./output/doctor/std_healthy.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    // a missing proxy, a dangling link, an incomplete toolchain, an
+    // interrupted download and a corrupt cache
+    std::fs::remove_file(temp_path.join("bin/format-compact")).unwrap();
+    std::os::unix::fs::symlink(temp_path.join("nowhere"), temp_path.join("bin/dangling")).unwrap();
+    std::fs::remove_file(toolchain.join("fixup-compact")).unwrap();
+    std::fs::create_dir_all(temp_path.join("tmp/0.98.0")).unwrap();
+    std::fs::write(temp_path.join("tmp/0.98.0/artifact.zip.partial"), b"zip").unwrap();
+    std::fs::create_dir_all(temp_path.join("cache")).unwrap();
+    std::fs::write(temp_path.join("cache/releases-0.json"), "{ corrupted").unwrap();
+
+    run_command(
+        &["--directory", &directory, "doctor"],
+        env(),
+        Some("./output/doctor/std_broken.txt"),
+        None,
+        &replacements,
+        Some(1),
+    );
+
+    // the toolchain can't be repaired without downloading it
+    run_command(
+        &["--directory", &directory, "doctor", "--fix"],
+        env(),
+        Some("./output/doctor/std_fixed.txt"),
+        None,
+        &replacements,
+        Some(1),
+    );
+
+    assert!(temp_path.join("bin/format-compact").is_file());
+    assert!(!temp_path.join("bin/dangling").is_symlink());
+    assert!(!temp_path.join("tmp/0.98.0").exists());
+
+    std::fs::copy(toolchain.join("compactc"), toolchain.join("fixup-compact")).unwrap();
+
+    run_command(
+        &["--directory", &directory, "--output", "json", "doctor"],
+        env(),
+        Some("./output/doctor/std_healthy_json.txt"),
+        None,
+        &replacements,
+        Some(0),
+    );
+
+    // not in PATH
+    run_command(
+        &["--directory", &directory, "--quiet", "doctor"],
+        Some(HashMap::from([(
+            "PATH".to_string(),
+            "/usr/bin".to_string(),
+        )])),
+        Some("./output/doctor/std_not_in_path.txt"),
+        None,
+        &replacements,
+        Some(1),
+    );
+}
+
+#[test]
+fn test_compact_doctor_missing_directory() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let missing = temp_dir.path().join("compact");
+    let directory = format!("{}", missing.display());
+
+    let path = std::env::var("PATH").unwrap_or_default();
+
+    run_command(
+        &["--directory", &directory, "doctor"],
+        Some(HashMap::from([(
+            "PATH".to_string(),
+            format!("{directory}/bin:{path}"),
+        )])),
+        Some("./output/doctor/std_missing_directory.txt"),
+        None,
+        &[("[DIRECTORY]", directory.as_str())],
+        Some(0),
+    );
+
+    // a diagnosis doesn't change anything
+    assert!(!missing.exists());
+}
@@ -0,0 +1,83 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(unix)]
+
+use crate::common::{get_version, install_fake_compiler, run_command};
+use std::collections::HashMap;
+
+mod common;
+
+#[test]
+fn test_compact_offline_update_installed() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+
+    install_fake_compiler(temp_path, "0.99.0");
+
+    run_command(
+        &[
+            "--offline",
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "update",
+            "0.99",
+        ],
+        None,
+        Some("./output/scenarios/sc2_std_already_installed.txt"),
+        None,
+        &[
+            ("[LATEST_COMPACTC_VERSION]", "0.99.0"),
+            ("[SYSTEM_VERSION]", get_version()),
+        ],
+        None,
+    );
+}
+
+#[test]
+fn test_compact_offline_env_update_not_installed() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+
+    install_fake_compiler(temp_path, "0.99.0");
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "update",
+            "0.98",
+        ],
+        Some(HashMap::from([(
+            "COMPACT_OFFLINE".to_string(),
+            "1".to_string(),
+        )])),
+        None,
+        Some("./output/update/err_offline_no_match.txt"),
+        &[],
+        Some(1),
+    );
+}
+
+#[test]
+fn test_compact_offline_self_check() {
+    run_command(
+        &["--offline", "self", "check"],
+        None,
+        None,
+        Some("./output/self/err_offline.txt"),
+        &[],
+        Some(1),
+    );
+}
@@ -0,0 +1,147 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(unix)]
+
+use crate::common::{create_fake_registry, get_version, install_fake_compiler, run_command};
+
+mod common;
+
+#[test]
+fn test_compact_json_update_list_clean() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let directory = format!("{}", temp_path.display());
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    install_fake_compiler(temp_path, "0.98.0");
+
+    run_command(
+        &[
+            "--output",
+            "json",
+            "--directory",
+            &directory,
+            "--registry",
+            &format!("{}", registry.display()),
+            "update",
+        ],
+        None,
+        Some("./output/json/update.txt"),
+        None,
+        &[("[SYSTEM_VERSION]", get_version())],
+        Some(0),
+    );
+
+    run_command(
+        &[
+            "--output",
+            "json",
+            "--directory",
+            &directory,
+            "list",
+            "--installed",
+        ],
+        None,
+        Some("./output/json/list_installed.txt"),
+        None,
+        &[],
+        Some(0),
+    );
+
+    run_command(
+        &[
+            "--output",
+            "json",
+            "--directory",
+            &directory,
+            "clean",
+            "--keep-current",
+        ],
+        None,
+        Some("./output/json/clean_keep.txt"),
+        None,
+        &[],
+        Some(0),
+    );
+}
+
+#[test]
+fn test_compact_json_format_check() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let directory = format!("{}", temp_path.display());
+    let sources = tempfile::tempdir().unwrap();
+    let sources_path = format!("{}", sources.path().display());
+
+    install_fake_compiler(temp_path, "0.98.0");
+
+    // the fake formatter prints its arguments
+    let a = sources.path().join("a.compact");
+    let b = sources.path().join("b.compact");
+    std::fs::write(&a, format!("format-compact 0.98.0 {}\n", a.display())).unwrap();
+    std::fs::write(&b, "x\n").unwrap();
+
+    run_command(
+        &["--directory", &directory, "update", "0.98.0"],
+        None,
+        Some("./output/scenarios/sc2_std_already_installed.txt"),
+        None,
+        &[
+            ("[LATEST_COMPACTC_VERSION]", "0.98.0"),
+            ("[SYSTEM_VERSION]", get_version()),
+        ],
+        Some(0),
+    );
+
+    run_command(
+        &[
+            "--output",
+            "json",
+            "--directory",
+            &directory,
+            "format",
+            "--check",
+            &format!("{}", a.display()),
+            &format!("{}", b.display()),
+        ],
+        None,
+        Some("./output/json/format_check.txt"),
+        Some("./output/json/err_format_check.txt"),
+        &[("[DIR]", &sources_path)],
+        Some(1),
+    );
+
+    // the report holds no escape sequences, even when styling is forced
+    run_command(
+        &[
+            "--output",
+            "json",
+            "--color",
+            "always",
+            "--directory",
+            &directory,
+            "format",
+            "--check",
+            &format!("{}", a.display()),
+            &format!("{}", b.display()),
+        ],
+        None,
+        Some("./output/json/format_check.txt"),
+        Some("./output/json/err_format_check.txt"),
+        &[("[DIR]", &sources_path)],
+        Some(1),
+    );
+}
@@ -0,0 +1,118 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+#![cfg(unix)]
+
+use crate::common::{assert_command_output, get_version, install_fake_compiler, run_command};
+use std::collections::HashMap;
+use std::path::Path;
+
+mod common;
+
+fn set_default(temp_path: &Path, version: &str) {
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "update",
+            version,
+        ],
+        None,
+        Some("./output/scenarios/sc2_std_already_installed.txt"),
+        None,
+        &[
+            ("[LATEST_COMPACTC_VERSION]", version),
+            ("[SYSTEM_VERSION]", get_version()),
+        ],
+        None,
+    );
+}
+
+#[test]
+fn test_proxy_default_toolchain() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+
+    install_fake_compiler(temp_path, "0.28.0");
+    install_fake_compiler(temp_path, "0.29.0");
+    set_default(temp_path, "0.29.0");
+
+    for program in ["compactc", "format-compact", "fixup-compact"] {
+        let bin = temp_path.join("bin").join(program);
+
+        assert_command_output(
+            Some(bin.to_str().unwrap()),
+            &["--version"],
+            None,
+            &format!("{program} 0.29.0 --version"),
+            "",
+            0,
+        );
+    }
+}
+
+#[test]
+fn test_proxy_plus_version() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+
+    install_fake_compiler(temp_path, "0.28.0");
+    install_fake_compiler(temp_path, "0.29.0");
+    set_default(temp_path, "0.29.0");
+
+    let bin = temp_path.join("bin").join("compactc");
+
+    assert_command_output(
+        Some(bin.to_str().unwrap()),
+        &["+0.28", "source.compact", "out"],
+        None,
+        "compactc 0.28.0 source.compact out",
+        "",
+        0,
+    );
+
+    assert_command_output(
+        Some(bin.to_str().unwrap()),
+        &["+0.30.0", "--version"],
+        None,
+        "",
+        "Error: Toolchain 0.30.0 is not installed, try `compact update 0.30.0'",
+        1,
+    );
+}
+
+#[test]
+fn test_proxy_toolchain_env() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+
+    install_fake_compiler(temp_path, "0.28.0");
+    install_fake_compiler(temp_path, "0.29.0");
+    set_default(temp_path, "0.29.0");
+
+    let bin = temp_path.join("bin").join("format-compact");
+
+    assert_command_output(
+        Some(bin.to_str().unwrap()),
+        &["file.compact"],
+        Some(HashMap::from([(
+            "COMPACT_TOOLCHAIN".to_string(),
+            "0.28.0".to_string(),
+        )])),
+        "format-compact 0.28.0 file.compact",
+        "",
+        0,
+    );
+}
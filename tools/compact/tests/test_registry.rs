@@ -0,0 +1,269 @@
+// This file is part of Compact.
+// Copyright (C) 2025 Midnight Foundation
+// SPDX-License-Identifier: Apache-2.0
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+// 	http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+#![cfg(unix)]
+
+use crate::common::{
+    assert_path_contains_string, create_fake_registry, get_version, public_key, public_key_env,
+    run_command,
+};
+use std::collections::HashMap;
+
+mod common;
+
+#[test]
+fn test_compact_update_from_registry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "--registry",
+            &format!("{}", registry.display()),
+            "update",
+            "0.99",
+        ],
+        Some(public_key_env()),
+        Some("./output/update/std_default.txt"),
+        None,
+        &[
+            ("[LATEST_COMPACTC_VERSION]", "0.99.0"),
+            ("[SYSTEM_VERSION]", get_version()),
+        ],
+        Some(0),
+    );
+
+    assert_path_contains_string(temp_path, &["0.99.0", get_version(), "compactc"]);
+}
+
+#[test]
+fn test_compact_offline_update_from_registry_url() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    run_command(
+        &[
+            "--offline",
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "update",
+        ],
+        Some(HashMap::from([
+            (
+                "COMPACT_REGISTRY".to_string(),
+                format!("file://{}", registry.display()),
+            ),
+            ("COMPACT_PUBLIC_KEY".to_string(), public_key()),
+        ])),
+        Some("./output/update/std_default.txt"),
+        None,
+        &[
+            ("[LATEST_COMPACTC_VERSION]", "0.99.0"),
+            ("[SYSTEM_VERSION]", get_version()),
+        ],
+        Some(0),
+    );
+}
+
+#[test]
+fn test_compact_changelog_from_registry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    for version in ["0.97.0", "0.98.0", "0.99.0"] {
+        create_fake_registry(registry_dir.path(), version);
+    }
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "--registry",
+            &format!("{}", registry_dir.path().display()),
+            "changelog",
+            "0.98.0",
+        ],
+        None,
+        Some("./output/changelog/std_range.txt"),
+        None,
+        &[],
+        Some(0),
+    );
+}
+
+#[test]
+fn test_compact_quiet_update_from_registry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "--registry",
+            &format!("{}", registry.display()),
+            "--quiet",
+            "update",
+            "0.99",
+        ],
+        Some(public_key_env()),
+        None,
+        None,
+        &[],
+        Some(0),
+    );
+
+    assert_path_contains_string(temp_path, &["0.99.0", get_version(), "compactc"]);
+
+    run_command(
+        &[
+            "--directory",
+            &format!("{}", temp_path.display()),
+            "--color",
+            "always",
+            "default",
+        ],
+        Some(HashMap::from([("NO_COLOR".to_string(), "1".to_string())])),
+        Some("./output/default/std_color_always.txt"),
+        None,
+        &[("[SYSTEM_VERSION]", get_version())],
+        Some(0),
+    );
+}
+
+#[test]
+fn test_compact_verbose_update_from_registry() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    let directory = format!("{}", temp_path.display());
+    let compact = |args: &[&str], log: Option<&str>| {
+        let mut command = std::process::Command::new("../../target/debug/compact");
+        command
+            .args(["--directory", &directory])
+            .args(args)
+            .env("COMPACT_PUBLIC_KEY", public_key())
+            .env_remove("COMPACT_LOG");
+
+        if let Some(log) = log {
+            command.env("COMPACT_LOG", log);
+        }
+
+        let output = command.output().unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+        assert!(output.status.success(), "{stderr}");
+
+        stderr
+    };
+
+    let stderr = compact(
+        &[
+            "--registry",
+            &format!("{}", registry.display()),
+            "-vv",
+            "update",
+            "0.99",
+        ],
+        None,
+    );
+
+    for trace in [
+        "loading the releases of the local registry",
+        "reading local file",
+        "archive extracted",
+        "default toolchain set",
+    ] {
+        assert!(stderr.contains(trace), "`{trace}' not traced:\n{stderr}");
+    }
+
+    // a single -v keeps the output of format and fixup unchanged
+    let stderr = compact(&["-v", "compile", "--version"], None);
+
+    assert!(!stderr.contains("DEBUG"), "{stderr}");
+
+    let stderr = compact(&["compile", "--version"], Some("compact::utils=debug"));
+
+    assert!(stderr.contains("running command"), "{stderr}");
+    assert!(stderr.contains("command exited"), "{stderr}");
+    assert!(!stderr.contains("default toolchain"), "{stderr}");
+}
+
+#[test]
+fn test_compact_update_from_registry_without_checksum() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    let index_path = registry.join("index.json");
+    let mut index: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&index_path).unwrap()).unwrap();
+    index["releases"][0]["assets"][0]
+        .as_object_mut()
+        .unwrap()
+        .remove("digest");
+    std::fs::write(&index_path, index.to_string()).unwrap();
+
+    let directory = format!("{}", temp_path.display());
+    let registry = format!("{}", registry.display());
+
+    run_command(
+        &[
+            "--directory",
+            &directory,
+            "--registry",
+            &registry,
+            "update",
+            "0.99",
+        ],
+        Some(public_key_env()),
+        Some("./output/update/std_unverified.txt"),
+        None,
+        &[("[SYSTEM_VERSION]", get_version())],
+        Some(0),
+    );
+
+    assert_path_contains_string(temp_path, &["0.99.0", get_version(), "compactc"]);
+}
+
+#[test]
+fn test_compact_update_from_registry_without_public_key() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let temp_path = temp_dir.path();
+    let registry_dir = tempfile::tempdir().unwrap();
+    let registry = create_fake_registry(registry_dir.path(), "0.99.0");
+
+    let output = std::process::Command::new("../../target/debug/compact")
+        .args(["--directory", &format!("{}", temp_path.display())])
+        .args(["--registry", &format!("{}", registry.display())])
+        .args(["update", "0.99"])
+        .env_remove("COMPACT_PUBLIC_KEY")
+        .output()
+        .unwrap();
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(0), "{stderr}");
+    assert!(!stderr.contains("signature"), "{stderr}");
+    assert_path_contains_string(temp_path, &["0.99.0", get_version(), "compactc"]);
+}